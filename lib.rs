@@ -5,10 +5,12 @@ mod contract_publish {
 
     // use ink::env::call::{ExecutionInput, Selector};
     // use ink::env::debug_println;
+    use ink::env::hash::Blake2x256;
     use ink::prelude::string::String;
+    use ink::prelude::vec::Vec;
     use ink::storage::Mapping;
 
-    #[derive(scale::Decode, scale::Encode)]
+    #[derive(Debug, PartialEq, Eq, scale::Decode, scale::Encode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
         CallerIsOwner,
@@ -18,6 +20,65 @@ mod contract_publish {
         InsufficientBalance,
         AlreadyOnList,
         TransferError,
+        ContractPaused,
+        SoldOut,
+        InvalidFee,
+        InvalidDuration,
+        RecipientIsOwner,
+        InvalidRoyalty,
+        NoteTooLong,
+        SongDelisted,
+        PriceLocked,
+        MetadataTooLarge,
+        InvalidShares,
+        NothingToWithdraw,
+        NoEscrow,
+        SaleEnded,
+        KeyOwnershipProofInvalid,
+        PendingIntentionsExist,
+        Decommissioned,
+        DeliveryIncomplete,
+        InvalidDeliveryChunk,
+        InvalidRating,
+        PendingLimitReached,
+        InvalidDiscount,
+        TooManyTags,
+        NotInterested,
+        NoPreview,
+        NotPublicYet,
+        Blacklisted,
+        AlreadyBuyer,
+        WouldBreakEscrow,
+        TooManyCoOwners,
+        ReceiptWindowOpen,
+        ProceedsHeld,
+        NoPendingIntentions,
+        BatchTooLarge,
+        EmptyImageAddress,
+        AccessExpired,
+        NoDeposit,
+        MetadataFrozen,
+        PriceAboveCap,
+        PauseReasonTooLong,
+        NotRecoveryAddress,
+        NoRecoveryInProgress,
+        RecoveryTimelocked,
+        BuyerIsOwner,
+        UnsupportedScheme,
+        IntentionsPaused,
+        PaymentIncomplete,
+        ZeroPrice,
+        NotBlacklisted,
+        DepositsHeld,
+        NotPayer,
+        NoChunkedDelivery,
+    }
+
+    #[derive(Debug, PartialEq, Eq, scale::Decode, scale::Encode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum DelistReason {
+        Manual,
+        SoldOut,
     }
 
     // #[derive(Debug)]
@@ -33,6 +94,9 @@ mod contract_publish {
         artist_name: String,
         album: String,
         watermark_image_ipfs: String,
+        image_content_hash: [u8; 32],
+        extra_metadata: Vec<u8>,
+        tags: Vec<String>,
     }
 
     #[derive(scale::Decode, scale::Encode)]
@@ -45,14 +109,27 @@ mod contract_publish {
         price: Balance,
     }
 
-    #[derive(scale::Decode, scale::Encode)]
+    #[derive(Debug, Clone, scale::Decode, scale::Encode)]
     #[cfg_attr(
         feature = "std",
         derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
     )]
     pub struct DistributedStorageInfo {
         location: String,
+        //Fallback location clients try when the primary one is unreachable
+        mirror_location: Option<String>,
         key: String,
+        note: Option<String>,
+        purchased_at: Timestamp,
+        //License terms in effect when the delivery was made
+        license_terms_hash: [u8; 32],
+        license_version: u32,
+        //End of a rental, None means the access never expires
+        expires_at: Option<Timestamp>,
+        //blake2x256 of the SCALE encoded (key, location) pair
+        delivery_commitment: [u8; 32],
+        //How `key` was encrypted, empty for the preview and the public release
+        encryption_scheme: String,
     }
 
     #[derive(scale::Decode, scale::Encode)]
@@ -62,6 +139,89 @@ mod contract_publish {
     )]
     pub struct BuyerPublicKey {
         key: String,
+        paid_so_far: Balance,
+        discount_bps: u16,
+        posted_at: Timestamp,
+        //Account that posted the intention and gets its refunds, differs from the buyer on gifts
+        payer: AccountId,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PendingBuyerKey {
+        key: String,
+        posted_at: Timestamp,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Config {
+        price: Balance,
+        owner: AccountId,
+        paused: bool,
+        max_editions: Option<u32>,
+        fee_bps: u16,
+        buyers_count: u32,
+        license_terms_hash: [u8; 32],
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct AuditSummary {
+        pending: u32,
+        confirmed: u32,
+        blacklisted: u32,
+        co_owners: u32,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct Financials {
+        total_escrowed: Balance,
+        total_revenue: Balance,
+        balance: Balance,
+        pending_withdrawals: Balance,
+    }
+
+    #[derive(scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct BuyerProfile {
+        //Same codes as buyer_state
+        state: u8,
+        escrow: Balance,
+        //Only known while the intention is pending
+        posted_at: Option<Timestamp>,
+        delivery: Option<DistributedStorageInfo>,
+        rating: Option<u8>,
+    }
+
+    /// Listing as announced by `SongPublish`, kept for clients that missed the event.
+    #[derive(Debug, Clone, scale::Decode, scale::Encode)]
+    #[cfg_attr(
+        feature = "std",
+        derive(scale_info::TypeInfo, ink::storage::traits::StorageLayout)
+    )]
+    pub struct PublishRecord {
+        owner: AccountId,
+        artist: String,
+        song_name: String,
+        price: Balance,
+        published_at: Timestamp,
     }
 
     /// Specify the ERC-20 result type.
@@ -88,6 +248,8 @@ mod contract_publish {
         owner: AccountId,
         #[ink(topic)]
         song_address: AccountId,
+        //Length in bytes of the posted public key, zero flags a malformed intention
+        key_len: u32,
     }
 
     #[ink::event]
@@ -100,6 +262,195 @@ mod contract_publish {
         song_address: AccountId,
     }
 
+    /// Full financial record of an approved sale, `net_to_owner` is before the revenue split.
+    #[ink(event)]
+    pub struct SaleCompleted {
+        #[ink(topic)]
+        buyer: AccountId,
+        price_paid: Balance,
+        net_to_owner: Balance,
+        fee: Balance,
+        #[ink(topic)]
+        song_address: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct EscrowChanged {
+        #[ink(topic)]
+        account: AccountId,
+        delta: Balance,
+        is_increase: bool,
+        total_escrowed: Balance,
+    }
+
+    #[ink(event)]
+    pub struct InstallmentPaid {
+        #[ink(topic)]
+        buyer: AccountId,
+        amount: Balance,
+        paid_so_far: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BuyIntentionCancelled {
+        #[ink(topic)]
+        buyer: AccountId,
+        refunded: Balance,
+    }
+
+    #[ink(event)]
+    pub struct IntentionRejected {
+        #[ink(topic)]
+        buyer: AccountId,
+        refunded: Balance,
+    }
+
+    #[ink(event)]
+    pub struct Withdrawal {
+        #[ink(topic)]
+        account: AccountId,
+        amount: Balance,
+    }
+
+    #[ink(event)]
+    pub struct SongDelisted {
+        #[ink(topic)]
+        song_address: AccountId,
+        reason: DelistReason,
+    }
+
+    #[ink(event)]
+    pub struct AutoPaused {
+        failures: u32,
+    }
+
+    #[ink(event)]
+    pub struct AccessExpiredEvent {
+        #[ink(topic)]
+        buyer: AccountId,
+        expired_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct AccessExtended {
+        #[ink(topic)]
+        buyer: AccountId,
+        expires_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct OwnershipRecovered {
+        #[ink(topic)]
+        previous_owner: AccountId,
+        #[ink(topic)]
+        new_owner: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryInitiated {
+        #[ink(topic)]
+        recovery_address: AccountId,
+        claimable_at: Timestamp,
+    }
+
+    #[ink(event)]
+    pub struct RecoveryCancelled {
+        #[ink(topic)]
+        recovery_address: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct WatermarkUpdated {
+        watermark_image_ipfs: String,
+    }
+
+    #[ink(event)]
+    pub struct BlockedAttempt {
+        #[ink(topic)]
+        account: AccountId,
+    }
+
+    #[ink(event)]
+    pub struct BatchBlacklisted {
+        count: u32,
+    }
+
+    #[ink(event)]
+    pub struct DiscountCodeRemoved {
+        #[ink(topic)]
+        code_hash: Hash,
+    }
+
+    #[ink(event)]
+    pub struct LicenseTermsUpdated {
+        version: u32,
+        terms_hash: [u8; 32],
+    }
+
+    #[ink(event)]
+    pub struct RedeliveryRequested {
+        #[ink(topic)]
+        buyer: AccountId,
+        new_public_key: String,
+    }
+
+    #[ink(event)]
+    pub struct CustomPriceSet {
+        #[ink(topic)]
+        buyer: AccountId,
+        price: Balance,
+    }
+
+    #[ink(event)]
+    pub struct BuyerKeyForgotten {
+        #[ink(topic)]
+        buyer: AccountId,
+        refunded: Balance,
+    }
+
+    /// Encryption schemes clients know how to decrypt deliveries with. Buyer keys are
+    /// compressed secp256k1 (see `decode_public_key`), so only ECIES based schemes fit.
+    const SUPPORTED_ENCRYPTION_SCHEMES: [&str; 1] = ["AES-256-GCM+ECIES"];
+
+    /// Maximum size in bytes of the note attached to a buyer.
+    const MAX_NOTE_LEN: usize = 128;
+
+    /// Maximum size in bytes of the reason attached to a pause.
+    const MAX_PAUSE_REASON_LEN: usize = 128;
+
+    /// Maximum size in bytes of the client-defined song metadata.
+    const MAX_EXTRA_METADATA_LEN: usize = 1024;
+
+    /// Maximum number of tags describing a song.
+    const MAX_TAGS: usize = 10;
+
+    /// Maximum number of accounts sharing the sale revenue.
+    const MAX_PAYEES: usize = 10;
+
+    /// Maximum number of accounts answered by a batch membership query.
+    const MAX_BATCH_QUERY: usize = 100;
+
+    /// Maximum number of custom prices set in a single call.
+    const MAX_CUSTOM_PRICES_BATCH: usize = 50;
+
+    /// Maximum number of accounts blacklisted in one call.
+    const MAX_BLACKLIST_BATCH: usize = 100;
+
+    /// Maximum number of pending intentions inspected by a single scan.
+    const MAX_PENDING_SCAN: usize = 100;
+
+    /// Maximum number of co-owners besides the owner.
+    const MAX_CO_OWNERS: usize = 10;
+
+    /// Consecutive failed owner payouts after which the contract pauses itself.
+    const AUTO_PAUSE_TRANSFER_FAILURES: u32 = 3;
+
+    /// Time a buyer has to confirm receipt before the owner can release the proceeds, 14 days.
+    const RECEIPT_CONFIRMATION_PERIOD: Timestamp = 14 * 24 * 60 * 60 * 1000;
+
+    /// Time the owner has to cancel a recovery before it can be claimed, 7 days.
+    const RECOVERY_DELAY: Timestamp = 7 * 24 * 60 * 60 * 1000;
+
     #[ink(storage)]
     pub struct ContractPublish {
         //Song info
@@ -112,12 +463,139 @@ mod contract_publish {
         buyers: Mapping<AccountId, DistributedStorageInfo>,
         ///List of users with intention to buy
         possible_buyers_keys: Mapping<AccountId, BuyerPublicKey>,
+        //Stops new intentions, approvals, deliveries and payouts, refunds stay open
+        paused: bool,
+        //Maximum number of buyers, None means unlimited
+        max_editions: Option<u32>,
+        //Platform fee in basis points taken from every sale
+        fee_bps: u16,
+        //Account receiving the platform fee
+        fee_recipient: AccountId,
+        //Number of confirmed buyers
+        buyers_count: u32,
+        //Owner payouts that failed in a row
+        consecutive_transfer_failures: u32,
+        //Confirmed buyers, in order of approval
+        buyers_index: Vec<AccountId>,
+        //Accounts with a pending buy intention
+        pending_index: Vec<AccountId>,
+        //Number of pending buy intentions
+        pending_count: u32,
+        //Value held in escrow for pending intentions
+        total_escrowed: Balance,
+        //Artist cut on secondary sales in basis points
+        resale_royalty_bps: u16,
+        //Song withdrawn from sale by the owner
+        delisted: bool,
+        //Once set the price can no longer change
+        price_locked: bool,
+        //Revenue split in basis points, empty means the owner is paid directly
+        payees: Vec<(AccountId, u16)>,
+        //Sale proceeds credited to payees and not yet withdrawn
+        pending_withdrawals: Mapping<AccountId, Balance>,
+        //Buyers may escrow the price over several payments
+        installments_enabled: bool,
+        //No new buy intentions are accepted from this moment on
+        sale_ends_at: Option<Timestamp>,
+        //Permanently retired, only getters and payee withdrawals keep working
+        decommissioned: bool,
+        //Encrypted key chunks of deliveries not finalized yet
+        delivery_chunks: Mapping<(AccountId, u16), Vec<u8>>,
+        //Number of chunks expected for each in-progress delivery
+        delivery_chunk_counts: Mapping<AccountId, u16>,
+        //Idempotency keys of approvals already processed
+        processed_keys: Mapping<[u8; 32], ()>,
+        //Account receiving the owner's sale proceeds
+        payout_address: AccountId,
+        //Rating from 1 to 5 given by each buyer
+        ratings: Mapping<AccountId, u8>,
+        //Sum and number of ratings, used for the average
+        rating_sum: u64,
+        rating_count: u32,
+        //Maximum number of pending intentions, None means unlimited
+        max_pending: Option<u32>,
+        //Discount in basis points by blake2 hash of the code
+        discount_codes: Mapping<Hash, u16>,
+        //Encrypted preview clip for interested accounts
+        preview_delivery: Option<DistributedStorageInfo>,
+        //Accounts that registered interest in the song
+        interested: Mapping<AccountId, ()>,
+        //Delivery anyone can read once public_after has passed
+        public_delivery: Option<DistributedStorageInfo>,
+        public_after: Option<Timestamp>,
+        //Decimals front-ends use to display the price
+        decimals: u8,
+        //Escrow released to the seller side by every approval
+        total_revenue: Balance,
+        //Sum of pending_withdrawals, mappings can't be enumerated
+        total_pending_withdrawals: Balance,
+        //Accounts that can't post buy intentions
+        blacklist: Mapping<AccountId, ()>,
+        blacklisted_count: u32,
+        //Band members sharing the song with the owner, they can approve and deliver to buyers
+        co_owners: Mapping<AccountId, ()>,
+        //Co-owners, in order of addition
+        co_owners_index: Vec<AccountId>,
+        //Construction time listing snapshot
+        publish_record: PublishRecord,
+        //Time after posting at which a pending intention can be swept, None means never
+        intention_ttl: Option<Timestamp>,
+        //Hash of the off-chain license text buyers agree to
+        license_terms_hash: [u8; 32],
+        //Bumped on every terms update, the construction terms are version 1
+        license_version: u32,
+        //Negotiated prices replacing the list price for specific buyers
+        custom_prices: Mapping<AccountId, Balance>,
+        //Length of the access sold, None sells it forever
+        rental_duration: Option<Timestamp>,
+        //Refundable deposit paid on top of the price with every intention
+        deposit_amount: Balance,
+        //Deposits held per account, returned on refund or by release_deposit
+        deposits: Mapping<AccountId, Balance>,
+        total_deposits: Balance,
+        //Song metadata can no longer be edited, irreversible
+        metadata_frozen: bool,
+        //Seller proceeds are held until the buyer confirms receipt
+        receipt_required: bool,
+        //Proceeds of each sale waiting for the buyer's confirmation
+        held_proceeds: Mapping<AccountId, Balance>,
+        total_held: Balance,
+        //Highest price set_price accepts, None means uncapped
+        max_price: Option<Balance>,
+        //Distinct accounts that bought over the contract's life, never decremented
+        lifetime_buyers: u32,
+        //Why the contract is paused, cleared on unpause
+        pause_reason: Option<String>,
+        //Account allowed to claim ownership if the owner loses their key
+        recovery_address: Option<AccountId>,
+        recovery_initiated_at: Option<Timestamp>,
+        //Currency code and scaled rate for fiat display, never used for payments
+        display_rate: Option<(String, u128)>,
+        //New public keys of buyers waiting for their content to be re-encrypted
+        redelivery_requests: Mapping<AccountId, String>,
+        redelivery_index: Vec<AccountId>,
+        //When set, anything paid above the price is sent back instead of escrowed
+        refund_overpayments: bool,
+        //Most recent surplus sent back to each paying account
+        last_overpayments: Mapping<AccountId, Balance>,
+        //Block the contract was published in
+        created_at_block: BlockNumber,
+        //Account royalty_info points resale royalties to, the owner unless changed
+        royalty_recipient: AccountId,
+        //Closes new intentions only, pending ones can still be approved
+        intentions_paused: bool,
+        //Account that paid each held deposit, the gifter for gifted intentions
+        depositors: Mapping<AccountId, AccountId>,
+        //Every account that ever bought, so lifetime_buyers counts each one once
+        past_buyers: Mapping<AccountId, ()>,
     }
 
     impl ContractPublish {
         //------------------------------CONSTRUCTOR------------------------------
 
-        /// Publica tu cancion almacenada en IPFS.
+        /// Publica tu cancion almacenada en IPFS. Las etiquetas se pasan a minusculas sin
+        /// repetidas y solo se guardan las primeras 10, `publish_song_checked` las rechaza.
+        /// Un precio de cero hace fallar el despliegue, ninguna compra podria aprobarse.
         #[ink(constructor)]
         pub fn publish_song(
             song_name: String,
@@ -126,7 +604,18 @@ mod contract_publish {
             song_duration: String,
             album_name: String,
             image_address: String,
+            image_content_hash: [u8; 32],
+            max_pending: Option<u32>,
+            tags: Vec<String>,
+            decimals: u8,
+            license_terms_hash: [u8; 32],
+            deposit_amount: Balance,
         ) -> Self {
+            assert!(song_price > 0, "song_price must be above zero");
+
+            let mut tags = Self::normalize_tags(tags);
+            tags.truncate(MAX_TAGS);
+
             let owner = Self::env().caller();
 
             Self::env().emit_event(SongPublish {
@@ -136,6 +625,14 @@ mod contract_publish {
                 song_name: song_name.clone(),
             });
 
+            let publish_record = PublishRecord {
+                owner,
+                artist: author_name.clone(),
+                song_name: song_name.clone(),
+                price: song_price,
+                published_at: Self::env().block_timestamp(),
+            };
+
             Self {
                 song_info: SongInfo {
                     album: album_name,
@@ -143,12 +640,124 @@ mod contract_publish {
                     song_duration,
                     song_name,
                     watermark_image_ipfs: image_address,
+                    image_content_hash,
+                    extra_metadata: Vec::new(),
+                    tags,
                 },
                 owner,
                 price: song_price,
                 buyers: Mapping::default(),
                 possible_buyers_keys: Mapping::default(),
+                paused: false,
+                max_editions: None,
+                fee_bps: 0,
+                fee_recipient: owner,
+                buyers_count: 0,
+                consecutive_transfer_failures: 0,
+                buyers_index: Vec::new(),
+                pending_index: Vec::new(),
+                pending_count: 0,
+                total_escrowed: 0,
+                resale_royalty_bps: 0,
+                delisted: false,
+                price_locked: false,
+                payees: Vec::new(),
+                pending_withdrawals: Mapping::default(),
+                installments_enabled: false,
+                sale_ends_at: None,
+                decommissioned: false,
+                delivery_chunks: Mapping::default(),
+                delivery_chunk_counts: Mapping::default(),
+                processed_keys: Mapping::default(),
+                payout_address: owner,
+                ratings: Mapping::default(),
+                rating_sum: 0,
+                rating_count: 0,
+                max_pending,
+                discount_codes: Mapping::default(),
+                preview_delivery: None,
+                interested: Mapping::default(),
+                public_delivery: None,
+                public_after: None,
+                decimals,
+                total_revenue: 0,
+                total_pending_withdrawals: 0,
+                blacklist: Mapping::default(),
+                blacklisted_count: 0,
+                co_owners: Mapping::default(),
+                co_owners_index: Vec::new(),
+                publish_record,
+                intention_ttl: None,
+                license_terms_hash,
+                license_version: 1,
+                custom_prices: Mapping::default(),
+                rental_duration: None,
+                deposit_amount,
+                deposits: Mapping::default(),
+                total_deposits: 0,
+                metadata_frozen: false,
+                receipt_required: false,
+                held_proceeds: Mapping::default(),
+                total_held: 0,
+                max_price: None,
+                lifetime_buyers: 0,
+                pause_reason: None,
+                recovery_address: None,
+                recovery_initiated_at: None,
+                display_rate: None,
+                redelivery_requests: Mapping::default(),
+                redelivery_index: Vec::new(),
+                refund_overpayments: false,
+                last_overpayments: Mapping::default(),
+                created_at_block: Self::env().block_number(),
+                royalty_recipient: owner,
+                intentions_paused: false,
+                depositors: Mapping::default(),
+                past_buyers: Mapping::default(),
+            }
+        }
+
+        /// Publica tu cancion validando que la duracion sea "mm:ss" o segundos, que el precio
+        /// no sea cero y que no queden mas de 10 etiquetas distintas.
+        #[ink(constructor)]
+        pub fn publish_song_checked(
+            song_name: String,
+            song_price: Balance,
+            author_name: String,
+            song_duration: String,
+            album_name: String,
+            image_address: String,
+            image_content_hash: [u8; 32],
+            max_pending: Option<u32>,
+            tags: Vec<String>,
+            decimals: u8,
+            license_terms_hash: [u8; 32],
+            deposit_amount: Balance,
+        ) -> ClientResult<Self> {
+            Self::validate_duration(&song_duration)?;
+
+            if song_price == 0 {
+                return Err(Error::ZeroPrice);
+            }
+
+            if Self::normalize_tags(tags.clone()).len() > MAX_TAGS {
+                return Err(Error::TooManyTags);
             }
+
+            return Ok(Self::publish_song(
+                song_name,
+                song_price,
+                author_name,
+                song_duration,
+                album_name,
+                image_address,
+                image_content_hash,
+                max_pending,
+                tags,
+                decimals,
+                license_terms_hash,
+                deposit_amount,
+            ));
         }
 
         //Messages
@@ -161,6 +770,9 @@ mod contract_publish {
                 artist_name: self.song_info.artist_name.clone(),
                 album: self.song_info.album.clone(),
                 watermark_image_ipfs: self.song_info.watermark_image_ipfs.clone(),
+                image_content_hash: self.song_info.image_content_hash,
+                extra_metadata: self.song_info.extra_metadata.clone(),
+                tags: self.song_info.tags.clone(),
             };
 
             return ClientSongInfoResponse {
@@ -169,153 +781,5146 @@ mod contract_publish {
             };
         }
 
-        #[ink(message, payable)]
-        pub fn post_buy_intention(&mut self, buyer_public_key: String) -> ClientResult<String> {
-            if Self::is_caller_owner(&self) {
-                return Err(Error::CallerIsOwner);
-            }
+        #[ink(message)]
+        pub fn get_song_address(&self) -> AccountId {
+            return self.env().account_id();
+        }
 
-            if self.possible_buyers_keys.contains(&self.env().caller()) {
-                return Err(Error::AlreadyOnList);
-            }
+        /// Blake2 256 hash of the song name, lets indexers match a known name.
+        #[ink(message)]
+        pub fn get_song_name_hash(&self) -> Hash {
+            let digest = self
+                .env()
+                .hash_bytes::<Blake2x256>(self.song_info.song_name.as_bytes());
 
-            if self.env().transferred_value() < self.price {
-                return Err(Error::InsufficientBalance);
-            }
+            return Hash::from(digest);
+        }
 
-            self.possible_buyers_keys.insert(
-                self.env().caller(),
-                &BuyerPublicKey {
-                    key: buyer_public_key,
-                },
-            );
+        /// Raw price along with the decimals to display it with.
+        #[ink(message)]
+        pub fn get_price_scaled(&self) -> (Balance, u8) {
+            return (self.price, self.decimals);
+        }
 
-            self.env().emit_event(SongBuyIntent {
-                from: self.env().caller(),
+        #[ink(message)]
+        pub fn get_config(&self) -> Config {
+            return Config {
+                price: self.price,
                 owner: self.owner,
-                song_address: self.env().account_id(),
-            });
+                paused: self.paused,
+                max_editions: self.max_editions,
+                fee_bps: self.fee_bps,
+                buyers_count: self.buyers_count,
+                license_terms_hash: self.license_terms_hash,
+            };
+        }
 
-            return Ok(String::from("Buy intention posted"));
+        /// Counters are maintained on every change, no mapping is enumerated.
+        #[ink(message)]
+        pub fn audit_summary(&self) -> AuditSummary {
+            return AuditSummary {
+                pending: self.pending_count,
+                confirmed: self.buyers_count,
+                blacklisted: self.blacklisted_count,
+                co_owners: self.co_owners_index.len() as u32,
+            };
         }
 
+        /// Empty when the sales are paid to the owner directly.
         #[ink(message)]
-        pub fn get_buyer_public_key(&self, buyer_key: AccountId) -> ClientResult<String> {
-            if !Self::is_caller_owner(&self) {
-                return Err(Error::CallerIsNotOwner);
-            }
+        pub fn get_payees(&self) -> Vec<(AccountId, u16)> {
+            return self.payees.clone();
+        }
 
-            let posible_user_key = self.possible_buyers_keys.get(buyer_key);
+        #[ink(message)]
+        pub fn get_display_rate(&self) -> Option<(String, u128)> {
+            return self.display_rate.clone();
+        }
 
-            match posible_user_key {
-                None => return Err(Error::NotOnPossibleBuyersList),
-                Some(key) => return Ok(key.key),
-            }
+        #[ink(message)]
+        pub fn get_pause_reason(&self) -> Option<String> {
+            return self.pause_reason.clone();
         }
 
+        /// Unlike `buyers_count` this keeps counting revoked buyers, an account revoked
+        /// and approved again is only counted once.
         #[ink(message)]
-        pub fn set_new_allowed_buyer(
-            &mut self,
-            encripted_symmetric_key: String,
-            ipfs_song_address: String,
-            buyer: AccountId,
-        ) -> ClientResult<String> {
+        pub fn get_lifetime_buyers(&self) -> u32 {
+            return self.lifetime_buyers;
+        }
 
-            if !self.possible_buyers_keys.contains(buyer) {
-                return Err(Error::NotOnPossibleBuyersList)
-            }
+        #[ink(message)]
+        pub fn is_metadata_frozen(&self) -> bool {
+            return self.metadata_frozen;
+        }
 
-            if self.env().transfer(self.owner, self.price).is_err() {
-                return Err(Error::TransferError);
-            }
+        #[ink(message)]
+        pub fn get_license_version(&self) -> u32 {
+            return self.license_version;
+        }
 
-            self.possible_buyers_keys.remove(buyer);
+        /// Token contract payments are made in, None means the native currency. Only native
+        /// payments are supported for now so this is always None.
+        #[ink(message)]
+        pub fn get_payment_method(&self) -> Option<AccountId> {
+            return None;
+        }
 
-            let new_saved_entry = DistributedStorageInfo {
-                location: ipfs_song_address,
-                key: encripted_symmetric_key,
-            };
+        #[ink(message)]
+        pub fn get_publish_record(&self) -> PublishRecord {
+            return self.publish_record.clone();
+        }
 
-            self.buyers.insert(buyer, &new_saved_entry);
+        #[ink(message)]
+        pub fn get_created_block(&self) -> BlockNumber {
+            return self.created_at_block;
+        }
 
-            self.env().emit_event(SongBuyConfirmation {
-                buyer,
-                author: self.song_info.artist_name.clone(),
-                song_address: self.env().account_id(),
+        /// True for the owner and co-owners, lets front-ends decide whether to show admin panels.
+        /// Co-owners can approve, reject and deliver but not change settings.
+        #[ink(message)]
+        pub fn am_i_admin(&self) -> bool {
+            return self.ensure_admin().is_ok();
+        }
+
+        #[ink(message)]
+        pub fn get_co_owners(&self) -> Vec<AccountId> {
+            return self.co_owners_index.clone();
+        }
+
+        /// Only checks the co-owner list, so the primary owner gets false unless added to it.
+        /// `am_i_admin` covers both roles.
+        #[ink(message)]
+        pub fn is_co_owner(&self, account: AccountId) -> bool {
+            return self.co_owners.contains(account);
+        }
+
+        /// Reconciliation snapshot, `balance` should cover `total_escrowed` plus
+        /// `pending_withdrawals`.
+        #[ink(message)]
+        pub fn financials(&self) -> Financials {
+            return Financials {
+                total_escrowed: self.total_escrowed,
+                total_revenue: self.total_revenue,
+                balance: self.env().balance(),
+                pending_withdrawals: self.total_pending_withdrawals,
+            };
+        }
+
+        /// EIP-2981 style query used by resale marketplaces.
+        #[ink(message)]
+        pub fn royalty_info(&self, sale_price: Balance) -> (AccountId, Balance) {
+            let royalty =
+                sale_price.saturating_mul(Balance::from(self.resale_royalty_bps)) / 10_000;
+
+            return (self.royalty_recipient, royalty);
+        }
+
+        #[ink(message)]
+        pub fn is_price_locked(&self) -> bool {
+            return self.price_locked;
+        }
+
+        /// Pending buyers whose escrow no longer covers the price, e.g. after a price raise.
+        #[ink(message)]
+        pub fn get_underfunded_buyers(&self) -> Vec<AccountId> {
+            return self
+                .pending_index
+                .iter()
+                .take(MAX_PENDING_SCAN)
+                .filter(|account| {
+                    self.possible_buyers_keys
+                        .get(**account)
+                        .is_some_and(|intention| intention.paid_so_far < self.amount_due(**account))
+                })
+                .copied()
+                .collect();
+        }
+
+        /// Each account holds at most one edition, so this is 0 or 1.
+        #[ink(message)]
+        pub fn editions_owned(&self, account: AccountId) -> u32 {
+            return u32::from(self.buyers.contains(account));
+        }
+
+        /// 0 when unknown, 1 with a pending intention, 2 once confirmed as buyer.
+        #[ink(message)]
+        pub fn buyer_state(&self, account: AccountId) -> u8 {
+            if self.buyers.contains(account) {
+                return 2;
+            }
+
+            if self.possible_buyers_keys.contains(account) {
+                return 1;
+            }
+
+            return 0;
+        }
+
+        /// Everything stored about `account` in one call, for support requests.
+        #[ink(message)]
+        pub fn buyer_profile(&self, account: AccountId) -> ClientResult<BuyerProfile> {
+            self.ensure_admin()?;
+
+            return Ok(BuyerProfile {
+                state: self.buyer_state(account),
+                escrow: self.escrowed_amount(account),
+                posted_at: self
+                    .possible_buyers_keys
+                    .get(account)
+                    .map(|intention| intention.posted_at),
+                delivery: self.buyers.get(account),
+                rating: self.ratings.get(account),
+            });
+        }
+
+        /// Single availability flag for front-ends, account specific guards are left to
+        /// `can_buy`.
+        #[ink(message)]
+        pub fn buying_open(&self) -> bool {
+            return !self.decommissioned
+                && !self.paused
+                && !self.intentions_paused
+                && !self.delisted
+                && !self.is_sold_out()
+                && !self.is_sale_ended();
+        }
+
+        /// Dry run of the `post_buy_intention` guards for `account`, minus the value check.
+        #[ink(message)]
+        pub fn can_buy(&self, account: AccountId) -> ClientResult<()> {
+            self.ensure_active()?;
+
+            if account == self.owner {
+                return Err(Error::CallerIsOwner);
+            }
+
+            return self.ensure_can_buy(account);
+        }
+
+        #[ink(message)]
+        pub fn has_tag(&self, tag: String) -> bool {
+            return self.song_info.tags.contains(&tag.to_lowercase());
+        }
+
+        /// Checks a code without revealing its discount.
+        #[ink(message)]
+        pub fn is_valid_discount_code(&self, code: String) -> bool {
+            return self.discount_codes.contains(self.hash_code(&code));
+        }
+
+        /// What is still owed before the account's intention covers its price.
+        #[ink(message)]
+        pub fn amount_remaining(&self, account: AccountId) -> Balance {
+            return self
+                .amount_due(account)
+                .saturating_sub(self.escrowed_amount(account));
+        }
+
+        /// Value the caller has locked in a pending intention, zero without one.
+        #[ink(message)]
+        pub fn my_escrow(&self) -> Balance {
+            return self.escrowed_amount(self.env().caller());
+        }
+
+        /// Surplus refunded on the account's latest overpaid intention, 0 if nothing was
+        /// ever refunded to it.
+        #[ink(message)]
+        pub fn last_overpayment(&self, account: AccountId) -> Balance {
+            return self.last_overpayments.get(account).unwrap_or(0);
+        }
+
+        /// How long the account's pending intention has been waiting.
+        #[ink(message)]
+        pub fn intention_age(&self, account: AccountId) -> ClientResult<Timestamp> {
+            match self.possible_buyers_keys.get(account) {
+                None => return Err(Error::NotOnPossibleBuyersList),
+                Some(intention) => {
+                    return Ok(self
+                        .env()
+                        .block_timestamp()
+                        .saturating_sub(intention.posted_at))
+                }
+            }
+        }
+
+        /// Platform fee that would be taken from a sale at `sale_price`.
+        #[ink(message)]
+        pub fn compute_fee(&self, sale_price: Balance) -> Balance {
+            return self.platform_fee(sale_price);
+        }
+
+        /// The payout address and what it would receive from one sale at the current
+        /// price after the platform fee and the revenue split. That's the owner's net
+        /// unless sales are paid out to another account.
+        #[ink(message)]
+        pub fn estimate_net_proceeds(&self) -> (AccountId, Balance) {
+            let net = self.price - self.platform_fee(self.price);
+
+            if self.payees.is_empty() {
+                return (self.payout_address, net);
+            }
+
+            let mut credited: Balance = 0;
+            let mut payout_part: Balance = 0;
+
+            for (payee, share) in self.payees.iter() {
+                let payee_amount = net.saturating_mul(Balance::from(*share)) / 10_000;
+                credited += payee_amount;
+
+                if *payee == self.payout_address {
+                    payout_part += payee_amount;
+                }
+            }
+
+            // Rounding leftovers go to the payout address, as in credit_payees
+            return (self.payout_address, payout_part + (net - credited));
+        }
+
+        /// Average rating scaled by 100, e.g. 450 means 4.5 stars. None until someone rates.
+        #[ink(message)]
+        pub fn get_average_rating(&self) -> Option<u32> {
+            if self.rating_count == 0 {
+                return None;
+            }
+
+            return Some((self.rating_sum * 100 / u64::from(self.rating_count)) as u32);
+        }
+
+        /// Number of buyers who rated, re-rating doesn't add to it.
+        #[ink(message)]
+        pub fn get_rating_count(&self) -> u32 {
+            return self.rating_count;
+        }
+
+        #[ink(message)]
+        pub fn get_rating_by(&self, account: AccountId) -> Option<u8> {
+            return self.ratings.get(account);
+        }
+
+        /// Answers in input order, accounts past the first 100 are ignored.
+        #[ink(message)]
+        pub fn have_purchased(&self, accounts: Vec<AccountId>) -> Vec<bool> {
+            return accounts
+                .iter()
+                .take(MAX_BATCH_QUERY)
+                .map(|account| self.buyers.contains(account))
+                .collect();
+        }
+
+        /// The account's custom price when one was negotiated, the list price otherwise.
+        #[ink(message)]
+        pub fn get_effective_price(&self, account: AccountId) -> Balance {
+            return self.effective_price(account);
+        }
+
+        #[ink(message)]
+        pub fn get_pending_withdrawal(&self, account: AccountId) -> Balance {
+            return self.pending_withdrawals.get(account).unwrap_or(0);
+        }
+
+        #[ink(message)]
+        pub fn get_sale_end(&self) -> Option<Timestamp> {
+            return self.sale_ends_at;
+        }
+
+        /// Countdown to the sale end, Some(0) once it has passed and None without an end.
+        #[ink(message)]
+        pub fn time_until_sale_end(&self) -> Option<Timestamp> {
+            return self
+                .sale_ends_at
+                .map(|sale_ends_at| sale_ends_at.saturating_sub(self.env().block_timestamp()));
+        }
+
+        //------------------------------OWNER SETTINGS------------------------------
+
+        #[ink(message)]
+        pub fn set_price(&mut self, new_price: Balance) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.price_locked {
+                return Err(Error::PriceLocked);
+            }
+
+            // Pending buyers escrowed against the current price, it can only change
+            // once they are approved, refunded or cancelled.
+            if self.pending_count > 0 {
+                return Err(Error::PendingIntentionsExist);
+            }
+
+            if new_price == 0 {
+                return Err(Error::ZeroPrice);
+            }
+
+            if self
+                .max_price
+                .is_some_and(|max_price| new_price > max_price)
+            {
+                return Err(Error::PriceAboveCap);
+            }
+
+            self.price = new_price;
+
+            return Ok(String::from("Price updated"));
+        }
+
+        /// Guards set_price against fat-finger values, the current price is not checked.
+        #[ink(message)]
+        pub fn set_max_price(&mut self, max_price: Option<Balance>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.max_price = max_price;
+
+            return Ok(String::from("Price cap updated"));
+        }
+
+        /// Purely informational, the rate's scale is agreed on with the clients.
+        #[ink(message)]
+        pub fn set_display_rate(
+            &mut self,
+            display_rate: Option<(String, u128)>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.display_rate = display_rate;
+
+            return Ok(String::from("Display rate updated"));
+        }
+
+        /// Also cancels a recovery in progress, which is how the owner stops an unwanted one.
+        #[ink(message)]
+        pub fn set_recovery_address(
+            &mut self,
+            recovery_address: Option<AccountId>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if let (Some(previous), Some(_)) = (self.recovery_address, self.recovery_initiated_at) {
+                self.env().emit_event(RecoveryCancelled {
+                    recovery_address: previous,
+                });
+            }
+
+            self.recovery_address = recovery_address;
+            self.recovery_initiated_at = None;
+
+            return Ok(String::from("Recovery address updated"));
+        }
+
+        /// Starts the `RECOVERY_DELAY` countdown, calling it again restarts it. The
+        /// `RecoveryInitiated` event lets the owner notice and cancel an unwanted one.
+        #[ink(message)]
+        pub fn initiate_recovery(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_recovery_address()?;
+
+            let now = self.env().block_timestamp();
+            self.recovery_initiated_at = Some(now);
+
+            self.env().emit_event(RecoveryInitiated {
+                recovery_address: self.env().caller(),
+                claimable_at: now.saturating_add(RECOVERY_DELAY),
+            });
+
+            return Ok(String::from("Recovery initiated"));
+        }
+
+        /// Payout, fee and royalty accounts that still point at the previous owner
+        /// move to the new one as well.
+        #[ink(message)]
+        pub fn claim_recovery(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_recovery_address()?;
+
+            let initiated_at = self
+                .recovery_initiated_at
+                .ok_or(Error::NoRecoveryInProgress)?;
+
+            if self.env().block_timestamp() < initiated_at.saturating_add(RECOVERY_DELAY) {
+                return Err(Error::RecoveryTimelocked);
+            }
+
+            let previous_owner = self.owner;
+            self.owner = self.env().caller();
+
+            if self.payout_address == previous_owner {
+                self.payout_address = self.owner;
+            }
+
+            if self.fee_recipient == previous_owner {
+                self.fee_recipient = self.owner;
+            }
+
+            if self.royalty_recipient == previous_owner {
+                self.royalty_recipient = self.owner;
+            }
+
+            self.recovery_address = None;
+            self.recovery_initiated_at = None;
+
+            self.env().emit_event(OwnershipRecovered {
+                previous_owner,
+                new_owner: self.owner,
+            });
+
+            return Ok(String::from("Ownership recovered"));
+        }
+
+        /// Irreversibly freezes the current price.
+        #[ink(message)]
+        pub fn lock_price(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.price_locked = true;
+
+            return Ok(String::from("Price locked"));
+        }
+
+        /// Freezes sales and payouts while keeping cancellations and refunds open,
+        /// `set_intentions_paused` only closes new intentions. `reason` is ignored when
+        /// unpausing.
+        #[ink(message)]
+        pub fn set_paused(&mut self, paused: bool, reason: Option<String>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if reason
+                .as_ref()
+                .is_some_and(|reason| reason.len() > MAX_PAUSE_REASON_LEN)
+            {
+                return Err(Error::PauseReasonTooLong);
+            }
+
+            self.paused = paused;
+            self.pause_reason = if paused { reason } else { None };
+
+            return Ok(String::from("Paused state updated"));
+        }
+
+        /// Lets the owner clear the pending backlog while no new intentions come in.
+        #[ink(message)]
+        pub fn set_intentions_paused(&mut self, paused: bool) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.intentions_paused = paused;
+
+            return Ok(String::from("Intentions paused state updated"));
+        }
+
+        #[ink(message)]
+        pub fn set_max_editions(&mut self, max_editions: Option<u32>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.max_editions = max_editions;
+
+            return Ok(String::from("Max editions updated"));
+        }
+
+        /// Fee expressed in basis points, 10000 being the whole price.
+        #[ink(message)]
+        pub fn set_platform_fee(
+            &mut self,
+            fee_recipient: AccountId,
+            fee_bps: u16,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if fee_bps > 10_000 {
+                return Err(Error::InvalidFee);
+            }
+
+            self.fee_recipient = fee_recipient;
+            self.fee_bps = fee_bps;
+
+            return Ok(String::from("Platform fee updated"));
+        }
+
+        #[ink(message)]
+        pub fn set_resale_royalty(&mut self, resale_royalty_bps: u16) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if resale_royalty_bps > 10_000 {
+                return Err(Error::InvalidRoyalty);
+            }
+
+            self.resale_royalty_bps = resale_royalty_bps;
+
+            return Ok(String::from("Resale royalty updated"));
+        }
+
+        /// E.g. a rights-management organisation, it isn't updated when ownership changes.
+        #[ink(message)]
+        pub fn set_royalty_recipient(
+            &mut self,
+            royalty_recipient: AccountId,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.royalty_recipient = royalty_recipient;
+
+            return Ok(String::from("Royalty recipient updated"));
+        }
+
+        #[ink(message)]
+        pub fn delist_song(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.delisted = true;
+
+            self.env().emit_event(SongDelisted {
+                song_address: self.env().account_id(),
+                reason: DelistReason::Manual,
+            });
+
+            return Ok(String::from("Song delisted"));
+        }
+
+        /// Assures buyers the song they paid for won't be swapped, there is no unfreeze.
+        #[ink(message)]
+        pub fn freeze_metadata(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.metadata_frozen = true;
+
+            return Ok(String::from("Metadata frozen"));
+        }
+
+        /// Opaque bytes, clients agree on their own encoding.
+        #[ink(message)]
+        pub fn set_extra_metadata(&mut self, extra_metadata: Vec<u8>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.metadata_frozen {
+                return Err(Error::MetadataFrozen);
+            }
+
+            if extra_metadata.len() > MAX_EXTRA_METADATA_LEN {
+                return Err(Error::MetadataTooLarge);
+            }
+
+            self.song_info.extra_metadata = extra_metadata;
+
+            return Ok(String::from("Extra metadata updated"));
+        }
+
+        /// Replaces the cover art, the rest of the song info is left untouched.
+        #[ink(message)]
+        pub fn set_watermark_image(&mut self, ipfs: String) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.metadata_frozen {
+                return Err(Error::MetadataFrozen);
+            }
+
+            if ipfs.is_empty() {
+                return Err(Error::EmptyImageAddress);
+            }
+
+            self.song_info.watermark_image_ipfs = ipfs.clone();
+
+            self.env().emit_event(WatermarkUpdated {
+                watermark_image_ipfs: ipfs,
             });
 
-            return Ok(String::from("Client added to buyers list"));
+            return Ok(String::from("Watermark image updated"));
+        }
+
+        /// Shares in basis points adding up to 10000, an empty list pays the owner directly.
+        #[ink(message)]
+        pub fn set_payees(&mut self, payees: Vec<(AccountId, u16)>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            let total_shares: u32 = payees.iter().map(|(_, share)| u32::from(*share)).sum();
+
+            if payees.len() > MAX_PAYEES || (!payees.is_empty() && total_shares != 10_000) {
+                return Err(Error::InvalidShares);
+            }
+
+            self.payees = payees;
+
+            return Ok(String::from("Payees updated"));
+        }
+
+        /// When enabled, repeated buy intentions top up the escrow instead of failing.
+        #[ink(message)]
+        pub fn set_installments(&mut self, enabled: bool) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.installments_enabled = enabled;
+
+            return Ok(String::from("Installments updated"));
+        }
+
+        /// Sets negotiated prices for up to 50 buyers at once, returns how many were set.
+        /// Each entry emits its own `CustomPriceSet`.
+        #[ink(message)]
+        pub fn set_custom_prices(
+            &mut self,
+            entries: Vec<(AccountId, Balance)>,
+        ) -> ClientResult<u32> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if entries.len() > MAX_CUSTOM_PRICES_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+
+            // A free intention escrows nothing and could never be approved
+            if entries.iter().any(|(_, price)| *price == 0) {
+                return Err(Error::ZeroPrice);
+            }
+
+            // Same rule as set_price, a pending buyer already escrowed against their price
+            if entries
+                .iter()
+                .any(|(buyer, _)| self.possible_buyers_keys.contains(buyer))
+            {
+                return Err(Error::PendingIntentionsExist);
+            }
+
+            for (buyer, price) in entries.iter() {
+                self.custom_prices.insert(buyer, price);
+
+                self.env().emit_event(CustomPriceSet {
+                    buyer: *buyer,
+                    price: *price,
+                });
+            }
+
+            return Ok(entries.len() as u32);
+        }
+
+        /// Applies to future purchases, existing buyers keep the terms they bought under.
+        #[ink(message)]
+        pub fn set_license_terms(&mut self, new_hash: [u8; 32]) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.license_terms_hash = new_hash;
+            self.license_version += 1;
+
+            self.env().emit_event(LicenseTermsUpdated {
+                version: self.license_version,
+                terms_hash: new_hash,
+            });
+
+            return Ok(String::from("License terms updated"));
+        }
+
+        /// Sells time-limited access from now on, existing deliveries keep their expiry.
+        #[ink(message)]
+        pub fn set_rental_duration(
+            &mut self,
+            rental_duration: Option<Timestamp>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.rental_duration = rental_duration;
+
+            return Ok(String::from("Rental duration updated"));
+        }
+
+        /// When enabled, sale proceeds are credited for withdrawal only once the buyer
+        /// confirms receipt or the confirmation period runs out.
+        #[ink(message)]
+        pub fn set_receipt_required(&mut self, required: bool) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.receipt_required = required;
+
+            return Ok(String::from("Receipt confirmation updated"));
+        }
+
+        /// When enabled, intentions paying more than the price get the surplus back. Otherwise
+        /// the whole payment is escrowed and released with the sale.
+        #[ink(message)]
+        pub fn set_refund_overpayments(&mut self, enabled: bool) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.refund_overpayments = enabled;
+
+            return Ok(String::from("Overpayment refunds updated"));
+        }
+
+        #[ink(message)]
+        pub fn set_sale_end(&mut self, sale_ends_at: Option<Timestamp>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.sale_ends_at = sale_ends_at;
+
+            return Ok(String::from("Sale end updated"));
+        }
+
+        #[ink(message)]
+        pub fn set_intention_ttl(
+            &mut self,
+            intention_ttl: Option<Timestamp>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.intention_ttl = intention_ttl;
+
+            return Ok(String::from("Intention expiry updated"));
+        }
+
+        /// Proceeds go to `payout_address` while administration stays with the owner.
+        #[ink(message)]
+        pub fn set_payout_address(&mut self, payout_address: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.payout_address = payout_address;
+
+            return Ok(String::from("Payout address updated"));
+        }
+
+        /// Registers the blake2 hash of a code so the code itself never appears on-chain.
+        /// A full discount is rejected, approval needs something in escrow.
+        #[ink(message)]
+        pub fn add_discount_code(
+            &mut self,
+            code_hash: Hash,
+            discount_bps: u16,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if discount_bps >= 10_000 {
+                return Err(Error::InvalidDiscount);
+            }
+
+            self.discount_codes.insert(code_hash, &discount_bps);
+
+            return Ok(String::from("Discount code added"));
+        }
+
+        /// Intentions posted with a removed code pay the full price.
+        #[ink(message)]
+        pub fn remove_discount_code(&mut self, code_hash: Hash) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.discount_codes.remove(code_hash);
+
+            self.env().emit_event(DiscountCodeRemoved { code_hash });
+
+            return Ok(String::from("Discount code removed"));
+        }
+
+        #[ink(message)]
+        pub fn set_tags(&mut self, tags: Vec<String>) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.metadata_frozen {
+                return Err(Error::MetadataFrozen);
+            }
+
+            let tags = Self::normalize_tags(tags);
+            if tags.len() > MAX_TAGS {
+                return Err(Error::TooManyTags);
+            }
+
+            self.song_info.tags = tags;
+
+            return Ok(String::from("Tags updated"));
+        }
+
+        #[ink(message)]
+        pub fn add_to_blacklist(&mut self, account: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if !self.blacklist.contains(account) {
+                self.blacklist.insert(account, &());
+                self.blacklisted_count += 1;
+            }
+
+            return Ok(String::from("Account blacklisted"));
+        }
+
+        /// Blacklists up to 100 accounts at once, returns how many weren't blacklisted yet.
+        #[ink(message)]
+        pub fn add_many_to_blacklist(&mut self, accounts: Vec<AccountId>) -> ClientResult<u32> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if accounts.len() > MAX_BLACKLIST_BATCH {
+                return Err(Error::BatchTooLarge);
+            }
+
+            let mut count: u32 = 0;
+
+            for account in accounts {
+                if !self.blacklist.contains(account) {
+                    self.blacklist.insert(account, &());
+                    self.blacklisted_count += 1;
+                    count += 1;
+                }
+            }
+
+            self.env().emit_event(BatchBlacklisted { count });
+
+            return Ok(count);
+        }
+
+        #[ink(message)]
+        pub fn remove_from_blacklist(&mut self, account: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.blacklist.contains(account) {
+                self.blacklist.remove(account);
+                self.blacklisted_count -= 1;
+            }
+
+            return Ok(String::from("Account removed from blacklist"));
+        }
+
+        /// Rejected intentions revert along with their events, so monitoring tools that
+        /// see a failed attempt from a blacklisted account report it here.
+        #[ink(message)]
+        pub fn record_blocked_attempt(&mut self, account: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if !self.blacklist.contains(account) {
+                return Err(Error::NotBlacklisted);
+            }
+
+            self.env().emit_event(BlockedAttempt { account });
+
+            return Ok(String::from("Blocked attempt recorded"));
+        }
+
+        #[ink(message)]
+        pub fn add_co_owner(&mut self, account: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.co_owners.contains(account) {
+                return Err(Error::AlreadyOnList);
+            }
+
+            if self.co_owners_index.len() >= MAX_CO_OWNERS {
+                return Err(Error::TooManyCoOwners);
+            }
+
+            self.co_owners.insert(account, &());
+            self.co_owners_index.push(account);
+
+            return Ok(String::from("Co-owner added"));
+        }
+
+        #[ink(message)]
+        pub fn remove_co_owner(&mut self, account: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if let Some(position) = self
+                .co_owners_index
+                .iter()
+                .position(|co_owner| *co_owner == account)
+            {
+                self.co_owners.remove(account);
+                self.co_owners_index.remove(position);
+            }
+
+            return Ok(String::from("Co-owner removed"));
+        }
+
+        //------------------------------BUY FLOW------------------------------
+
+        /// `buyer_public_key` is a hex encoded compressed secp256k1 key and `key_ownership_proof`
+        /// its signature over the blake2 hash of the buyer account.
+        #[ink(message, payable)]
+        pub fn post_buy_intention(
+            &mut self,
+            buyer_public_key: String,
+            key_ownership_proof: [u8; 65],
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            if Self::is_caller_owner(&self) {
+                return Err(Error::CallerIsOwner);
+            }
+
+            let buyer = self.env().caller();
+            self.verify_key_ownership(buyer, &buyer_public_key, &key_ownership_proof)?;
+
+            return self.record_intention(buyer, buyer_public_key, 0);
+        }
+
+        /// Same as `post_buy_intention`, unknown or removed codes pay the full price.
+        #[ink(message, payable)]
+        pub fn post_buy_intention_with_code(
+            &mut self,
+            buyer_public_key: String,
+            key_ownership_proof: [u8; 65],
+            code: String,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            if Self::is_caller_owner(&self) {
+                return Err(Error::CallerIsOwner);
+            }
+
+            let buyer = self.env().caller();
+            self.verify_key_ownership(buyer, &buyer_public_key, &key_ownership_proof)?;
+
+            let discount_bps = self.discount_codes.get(self.hash_code(&code)).unwrap_or(0);
+
+            return self.record_intention(buyer, buyer_public_key, discount_bps);
+        }
+
+        /// Pays for the song on behalf of `recipient`, who becomes the buyer once approved.
+        #[ink(message, payable)]
+        pub fn post_buy_intention_for(
+            &mut self,
+            recipient: AccountId,
+            recipient_public_key: String,
+            key_ownership_proof: [u8; 65],
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            if recipient == self.owner {
+                return Err(Error::RecipientIsOwner);
+            }
+
+            self.verify_key_ownership(recipient, &recipient_public_key, &key_ownership_proof)?;
+
+            return self.record_intention(recipient, recipient_public_key, 0);
+        }
+
+        /// Withdraws the caller's pending intention and refunds its escrow.
+        #[ink(message)]
+        pub fn cancel_buy_intention(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            let buyer = self.env().caller();
+            let escrow = self.refund_intention(buyer)?;
+
+            self.env().emit_event(BuyIntentionCancelled {
+                buyer,
+                refunded: escrow,
+            });
+
+            return Ok(String::from("Buy intention cancelled"));
+        }
+
+        /// Erases the caller's pending public key, refunding the escrow like a cancellation.
+        /// Only pending keys can be forgotten, after delivery the key is no longer stored
+        /// but the delivery record is what grants access and is kept.
+        #[ink(message)]
+        pub fn forget_me(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            let buyer = self.env().caller();
+            let escrow = self.refund_intention(buyer)?;
+
+            self.env().emit_event(BuyerKeyForgotten {
+                buyer,
+                refunded: escrow,
+            });
+
+            return Ok(String::from("Buyer key forgotten"));
+        }
+
+        /// Owner side rejection, e.g. for spam, refunding the buyer's escrow.
+        #[ink(message)]
+        pub fn reject_intention(&mut self, buyer: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_admin()?;
+
+            let escrow = self.refund_intention(buyer)?;
+
+            self.env().emit_event(IntentionRejected {
+                buyer,
+                refunded: escrow,
+            });
+
+            return Ok(String::from("Buy intention rejected"));
+        }
+
+        /// Lottery drop, keeps one pseudo-randomly chosen pending buyer and refunds the
+        /// rest. The seed comes from the block number and timestamp, which block authors
+        /// can influence, so this is only fit for low-stakes drops.
+        #[ink(message)]
+        pub fn draw_winner(&mut self) -> ClientResult<AccountId> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.pending_index.is_empty() {
+                return Err(Error::NoPendingIntentions);
+            }
+
+            let seed = self.env().hash_encoded::<Blake2x256, _>(&(
+                self.env().block_number(),
+                self.env().block_timestamp(),
+            ));
+            let index = u32::from_le_bytes([seed[0], seed[1], seed[2], seed[3]]) as usize
+                % self.pending_index.len();
+            let winner = self.pending_index[index];
+
+            let losers: Vec<AccountId> = self
+                .pending_index
+                .iter()
+                .filter(|account| **account != winner)
+                .copied()
+                .collect();
+
+            for buyer in losers {
+                let escrow = self.refund_intention(buyer)?;
+
+                self.env().emit_event(IntentionRejected {
+                    buyer,
+                    refunded: escrow,
+                });
+            }
+
+            return Ok(winner);
+        }
+
+        /// `posted_at` lets the owner serve the longest waiting buyers first.
+        #[ink(message)]
+        pub fn get_buyer_public_key(&self, buyer_key: AccountId) -> ClientResult<PendingBuyerKey> {
+            self.ensure_admin()?;
+
+            let posible_user_key = self.possible_buyers_keys.get(buyer_key);
+
+            match posible_user_key {
+                None => return Err(Error::NotOnPossibleBuyersList),
+                Some(key) => {
+                    return Ok(PendingBuyerKey {
+                        key: key.key,
+                        posted_at: key.posted_at,
+                    })
+                }
+            }
+        }
+
+        #[ink(message)]
+        pub fn set_new_allowed_buyer(
+            &mut self,
+            encripted_symmetric_key: String,
+            ipfs_song_address: String,
+            mirror_location: Option<String>,
+            encryption_scheme: String,
+            buyer: AccountId,
+            note: Option<String>,
+            idempotency_key: Option<[u8; 32]>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+            self.ensure_admin()?;
+
+            // Retried approvals succeed without paying or delivering twice
+            if idempotency_key.is_some_and(|key| self.processed_keys.contains(key)) {
+                return Ok(String::from("Approval already processed"));
+            }
+
+            if !self.possible_buyers_keys.contains(buyer) {
+                return Err(Error::NotOnPossibleBuyersList)
+            }
+
+            if note.as_ref().is_some_and(|note| note.len() > MAX_NOTE_LEN) {
+                return Err(Error::NoteTooLong);
+            }
+
+            // Release what the buyer actually escrowed, the price may have changed since
+            let escrow = self.escrowed_amount(buyer);
+
+            if escrow == 0 {
+                return Err(Error::NoEscrow);
+            }
+
+            // Installment buyers are only approvable once `amount_remaining` reaches zero
+            if escrow < self.amount_due(buyer) {
+                return Err(Error::PaymentIncomplete);
+            }
+
+            self.complete_sale(
+                buyer,
+                escrow,
+                encripted_symmetric_key,
+                ipfs_song_address,
+                mirror_location,
+                encryption_scheme,
+                note,
+            )?;
+
+            self.remove_pending(buyer);
+            self.decrease_escrow(buyer, escrow);
+
+            if let Some(key) = idempotency_key {
+                self.processed_keys.insert(key, &());
+            }
+
+            return Ok(String::from("Client added to buyers list"));
+        }
+
+        /// A failed payout reverts the whole approval, counter included, so the owner's
+        /// tooling reports each approval that failed with `TransferError` here. Enough
+        /// failures in a row pause the contract, a successful payout resets the count.
+        #[ink(message)]
+        pub fn report_payout_failure(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            self.record_transfer_failure();
+
+            return Ok(String::from("Payout failure recorded"));
+        }
+
+        /// One-shot sale for flows where the owner operates the buyer UI, e.g. at a booth.
+        /// The owner pays the price on the buyer's behalf and the sale is approved in the
+        /// same call, the buyer never posts a public key.
+        #[ink(message, payable)]
+        pub fn direct_sale(
+            &mut self,
+            buyer: AccountId,
+            encrypted_key: String,
+            ipfs_address: String,
+            encryption_scheme: String,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+            self.ensure_owner()?;
+
+            if buyer == self.owner {
+                return Err(Error::BuyerIsOwner);
+            }
+
+            self.ensure_can_buy(buyer)?;
+
+            if self.possible_buyers_keys.contains(buyer) {
+                return Err(Error::AlreadyOnList);
+            }
+
+            let price = self.effective_price(buyer);
+            let mut paid = self.env().transferred_value();
+
+            if paid < price {
+                return Err(Error::InsufficientBalance);
+            }
+
+            if self.refund_overpayments && paid > price {
+                let surplus = paid - price;
+
+                if self.env().transfer(self.env().caller(), surplus).is_err() {
+                    return Err(Error::TransferError);
+                }
+
+                self.last_overpayments.insert(self.env().caller(), &surplus);
+                paid = price;
+            }
+
+            self.complete_sale(
+                buyer,
+                paid,
+                encrypted_key,
+                ipfs_address,
+                None,
+                encryption_scheme,
+                None,
+            )?;
+
+            return Ok(String::from("Client added to buyers list"));
+        }
+
+        /// Streams a large encrypted key to an approved buyer, the buyer can't read
+        /// the delivery until `finalize_delivery` assembles the chunks.
+        #[ink(message)]
+        pub fn set_delivery_chunk(
+            &mut self,
+            buyer: AccountId,
+            index: u16,
+            chunk: Vec<u8>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+            self.ensure_admin()?;
+
+            if !self.buyers.contains(buyer) {
+                return Err(Error::NotOnBuyersList);
+            }
+
+            let chunk_count = self.delivery_chunk_counts.get(buyer).unwrap_or(0);
+
+            if index >= chunk_count {
+                let new_count = index.checked_add(1).ok_or(Error::InvalidDeliveryChunk)?;
+                self.delivery_chunk_counts.insert(buyer, &new_count);
+            }
+
+            self.delivery_chunks.insert((buyer, index), &chunk);
+
+            return Ok(String::from("Delivery chunk stored"));
+        }
+
+        #[ink(message)]
+        pub fn finalize_delivery(&mut self, buyer: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+            self.ensure_admin()?;
+
+            let mut delivery = self.buyers.get(buyer).ok_or(Error::NotOnBuyersList)?;
+            let chunk_count = self
+                .delivery_chunk_counts
+                .get(buyer)
+                .ok_or(Error::DeliveryIncomplete)?;
+
+            let mut key_bytes = Vec::new();
+
+            for index in 0..chunk_count {
+                let chunk = self
+                    .delivery_chunks
+                    .get((buyer, index))
+                    .ok_or(Error::DeliveryIncomplete)?;
+                key_bytes.extend_from_slice(&chunk);
+            }
+
+            delivery.key = String::from_utf8(key_bytes).map_err(|_| Error::InvalidDeliveryChunk)?;
+            delivery.delivery_commitment =
+                self.delivery_commitment(&delivery.key, &delivery.location);
+            self.buyers.insert(buyer, &delivery);
+
+            self.clear_delivery_chunks(buyer);
+
+            return Ok(String::from("Delivery finalized"));
+        }
+
+        /// Drops a chunked upload that won't be finalized, the buyer reads their
+        /// previous delivery again.
+        #[ink(message)]
+        pub fn abort_delivery_chunks(&mut self, buyer: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_admin()?;
+
+            if !self.delivery_chunk_counts.contains(buyer) {
+                return Err(Error::NoChunkedDelivery);
+            }
+
+            self.clear_delivery_chunks(buyer);
+
+            return Ok(String::from("Delivery chunks discarded"));
+        }
+
+        /// For buyers who rotated their keys, asking again replaces the pending request.
+        /// The new key needs the same ownership proof as `post_buy_intention`.
+        #[ink(message)]
+        pub fn request_redelivery(
+            &mut self,
+            new_public_key: String,
+            key_ownership_proof: [u8; 65],
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            let buyer = self.env().caller();
+
+            if !self.buyers.contains(buyer) {
+                return Err(Error::NotOnBuyersList);
+            }
+
+            self.verify_key_ownership(buyer, &new_public_key, &key_ownership_proof)?;
+
+            if !self.redelivery_requests.contains(buyer) {
+                self.redelivery_index.push(buyer);
+            }
+
+            self.redelivery_requests.insert(buyer, &new_public_key);
+
+            self.env().emit_event(RedeliveryRequested {
+                buyer,
+                new_public_key,
+            });
+
+            return Ok(String::from("Redelivery requested"));
+        }
+
+        /// Replaces a buyer's key and location, clearing their redelivery request if any.
+        /// The rest of the delivery, e.g. its license terms and expiry, is kept.
+        #[ink(message)]
+        pub fn update_delivery(
+            &mut self,
+            buyer: AccountId,
+            encripted_symmetric_key: String,
+            ipfs_song_address: String,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+            self.ensure_admin()?;
+
+            let mut delivery = self.buyers.get(buyer).ok_or(Error::NotOnBuyersList)?;
+
+            delivery.delivery_commitment =
+                self.delivery_commitment(&encripted_symmetric_key, &ipfs_song_address);
+            delivery.key = encripted_symmetric_key;
+            delivery.location = ipfs_song_address;
+            self.buyers.insert(buyer, &delivery);
+
+            if self.redelivery_requests.contains(buyer) {
+                self.redelivery_requests.remove(buyer);
+                self.redelivery_index.retain(|account| *account != buyer);
+            }
+
+            return Ok(String::from("Delivery updated"));
+        }
+
+        /// Pending redeliveries with the new public key to encrypt for.
+        #[ink(message)]
+        pub fn get_redelivery_requests(&self) -> ClientResult<Vec<(AccountId, String)>> {
+            self.ensure_admin()?;
+
+            return Ok(self
+                .redelivery_index
+                .iter()
+                .filter_map(|account| {
+                    self.redelivery_requests
+                        .get(account)
+                        .map(|key| (*account, key))
+                })
+                .collect());
+        }
+
+        /// Releases the proceeds of the caller's purchase to the seller side.
+        #[ink(message)]
+        pub fn confirm_receipt(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+
+            let buyer = self.env().caller();
+
+            if !self.buyers.contains(buyer) {
+                return Err(Error::NotOnBuyersList);
+            }
+
+            self.release_held_proceeds(buyer)?;
+
+            return Ok(String::from("Receipt confirmed"));
+        }
+
+        /// Returns a buyer's deposit to whoever paid it once the owner is satisfied with the
+        /// license use.
+        #[ink(message)]
+        pub fn release_deposit(&mut self, buyer: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if !self.buyers.contains(buyer) {
+                return Err(Error::NotOnBuyersList);
+            }
+
+            let depositor = self.depositors.get(buyer).unwrap_or(buyer);
+            let deposit = self.take_deposit(buyer);
+
+            if deposit == 0 {
+                return Err(Error::NoDeposit);
+            }
+
+            if self.env().transfer(depositor, deposit).is_err() {
+                return Err(Error::TransferError);
+            }
+
+            return Ok(String::from("Deposit released"));
+        }
+
+        #[ink(message)]
+        pub fn get_deposit(&self, account: AccountId) -> Balance {
+            return self.deposits.get(account).unwrap_or(0);
+        }
+
+        /// Lets the owner collect proceeds the buyer never confirmed once
+        /// `RECEIPT_CONFIRMATION_PERIOD` has passed since the purchase.
+        #[ink(message)]
+        pub fn release_unconfirmed(&mut self, buyer: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_not_paused()?;
+            self.ensure_owner()?;
+
+            let delivery = self.buyers.get(buyer).ok_or(Error::NotOnBuyersList)?;
+
+            if self.env().block_timestamp()
+                < delivery
+                    .purchased_at
+                    .saturating_add(RECEIPT_CONFIRMATION_PERIOD)
+            {
+                return Err(Error::ReceiptWindowOpen);
+            }
+
+            self.release_held_proceeds(buyer)?;
+
+            return Ok(String::from("Proceeds released"));
+        }
+
+        /// Buyers rate the song from 1 to 5, rating again replaces the previous rating.
+        #[ink(message)]
+        pub fn rate_song(&mut self, rating: u8) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            let caller = self.env().caller();
+
+            if !self.buyers.contains(caller) {
+                return Err(Error::NotOnBuyersList);
+            }
+
+            if !(1..=5).contains(&rating) {
+                return Err(Error::InvalidRating);
+            }
+
+            match self.ratings.get(caller) {
+                None => self.rating_count += 1,
+                Some(previous) => self.rating_sum -= u64::from(previous),
+            }
+
+            self.rating_sum += u64::from(rating);
+            self.ratings.insert(caller, &rating);
+
+            return Ok(String::from("Song rated"));
+        }
+
+        #[ink(message)]
+        pub fn set_preview(
+            &mut self,
+            encripted_symmetric_key: String,
+            ipfs_preview_address: String,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            let delivery_commitment =
+                self.delivery_commitment(&encripted_symmetric_key, &ipfs_preview_address);
+
+            self.preview_delivery = Some(DistributedStorageInfo {
+                location: ipfs_preview_address,
+                mirror_location: None,
+                key: encripted_symmetric_key,
+                note: None,
+                purchased_at: self.env().block_timestamp(),
+                license_terms_hash: self.license_terms_hash,
+                license_version: self.license_version,
+                expires_at: None,
+                delivery_commitment,
+                encryption_scheme: String::new(),
+            });
+
+            return Ok(String::from("Preview updated"));
+        }
+
+        /// Lets a non-paying account read the preview clip delivery.
+        #[ink(message)]
+        pub fn register_interest(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+
+            self.interested.insert(self.env().caller(), &());
+
+            return Ok(String::from("Interest registered"));
+        }
+
+        #[ink(message)]
+        pub fn get_preview(&self) -> ClientResult<DistributedStorageInfo> {
+            if !self.interested.contains(self.env().caller()) {
+                return Err(Error::NotInterested);
+            }
+
+            return self.preview_delivery.clone().ok_or(Error::NoPreview);
+        }
+
+        /// Schedules the song to enter the public domain, `symmetric_key` is meant to be
+        /// readable by anyone. Passing None for `public_after` cancels the release.
+        #[ink(message)]
+        pub fn set_public_release(
+            &mut self,
+            symmetric_key: String,
+            ipfs_song_address: String,
+            public_after: Option<Timestamp>,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            let delivery_commitment = self.delivery_commitment(&symmetric_key, &ipfs_song_address);
+
+            self.public_after = public_after;
+            self.public_delivery = public_after.map(|_| DistributedStorageInfo {
+                location: ipfs_song_address,
+                mirror_location: None,
+                key: symmetric_key,
+                note: None,
+                purchased_at: self.env().block_timestamp(),
+                license_terms_hash: self.license_terms_hash,
+                license_version: self.license_version,
+                expires_at: None,
+                delivery_commitment,
+                encryption_scheme: String::new(),
+            });
+
+            return Ok(String::from("Public release updated"));
+        }
+
+        /// Readable by buyers at any time and by anyone once `public_after` has passed.
+        #[ink(message)]
+        pub fn get_public_delivery(&self) -> ClientResult<DistributedStorageInfo> {
+            let delivery = self.public_delivery.clone().ok_or(Error::NotPublicYet)?;
+            let is_public = self
+                .public_after
+                .is_some_and(|public_after| self.env().block_timestamp() >= public_after);
+
+            if !is_public && !self.buyers.contains(self.env().caller()) {
+                return Err(Error::NotPublicYet);
+            }
+
+            return Ok(delivery);
+        }
+
+        #[ink(message)]
+        pub fn get_address_and_key_buyer(&self) -> ClientResult<DistributedStorageInfo> {
+
+            if !self.buyers.contains(self.env().caller()) {
+                return Err(Error::NotOnBuyersList)
+            }
+
+            if self.delivery_chunk_counts.contains(self.env().caller()) {
+                return Err(Error::DeliveryIncomplete);
+            }
+
+            let buyer_data = self.buyers.get(self.env().caller());
+
+            match buyer_data {
+                None => return Err(Error::NotOnBuyersList),
+                Some(data) if self.is_access_expired(&data) => return Err(Error::AccessExpired),
+                Some(data) => return Ok(data)
+            }
+        }
+
+        /// Migration snapshot of confirmed buyers in approval order, at most 100 per page.
+        /// The delivery carries its license terms, the timestamp is its purchase time.
+        #[ink(message)]
+        pub fn export_deliveries(
+            &self,
+            start: u32,
+            limit: u32,
+        ) -> ClientResult<Vec<(AccountId, DistributedStorageInfo, Timestamp)>> {
+            self.ensure_owner()?;
+
+            return Ok(self
+                .buyers_index
+                .iter()
+                .skip(start as usize)
+                .take((limit as usize).min(MAX_BATCH_QUERY))
+                .filter_map(|buyer| {
+                    self.buyers.get(buyer).map(|delivery| {
+                        let purchased_at = delivery.purchased_at;
+                        (*buyer, delivery, purchased_at)
+                    })
+                })
+                .collect());
+        }
+
+        /// False for accounts that never bought, deliveries without expiry are always active.
+        #[ink(message)]
+        pub fn is_my_access_active(&self) -> bool {
+            return self
+                .buyers
+                .get(self.env().caller())
+                .is_some_and(|delivery| !self.is_access_expired(&delivery));
+        }
+
+        /// Mutating counterpart of `get_address_and_key_buyer` for rentals, returns whether
+        /// the caller's access is active and emits `AccessExpiredEvent` when it isn't.
+        #[ink(message)]
+        pub fn check_access(&mut self) -> ClientResult<bool> {
+            self.ensure_active()?;
+
+            let buyer = self.env().caller();
+            let delivery = self.buyers.get(buyer).ok_or(Error::NotOnBuyersList)?;
+
+            if !self.is_access_expired(&delivery) {
+                return Ok(true);
+            }
+
+            self.env().emit_event(AccessExpiredEvent {
+                buyer,
+                expired_at: delivery.expires_at.unwrap_or_default(),
+            });
+
+            return Ok(false);
+        }
+
+        /// Lets the caller check that the key and location they received match what the
+        /// owner committed to on approval, `expected` is hashed the same way as
+        /// `delivery_commitment`.
+        #[ink(message)]
+        pub fn verify_delivery(&self, expected: [u8; 32]) -> ClientResult<bool> {
+            let delivery = self
+                .buyers
+                .get(self.env().caller())
+                .ok_or(Error::NotOnBuyersList)?;
+
+            return Ok(delivery.delivery_commitment == expected);
+        }
+
+        /// Free rental extension, e.g. as compensation. Deliveries that never expire are left
+        /// untouched.
+        #[ink(message)]
+        pub fn grant_extension(
+            &mut self,
+            buyer: AccountId,
+            extra: Timestamp,
+        ) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            let mut delivery = self.buyers.get(buyer).ok_or(Error::NotOnBuyersList)?;
+
+            let Some(expires_at) = delivery.expires_at else {
+                return Ok(String::from("Access never expires"));
+            };
+
+            let expires_at = expires_at.saturating_add(extra);
+            delivery.expires_at = Some(expires_at);
+            self.buyers.insert(buyer, &delivery);
+
+            self.env().emit_event(AccessExtended { buyer, expires_at });
+
+            return Ok(String::from("Access extended"));
+        }
+
+        /// Removes a buyer's delivery record along with their rating and pending requests,
+        /// no refund is made. Held proceeds and deposits have to be settled first.
+        #[ink(message)]
+        pub fn revoke_buyer(&mut self, buyer: AccountId) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if !self.buyers.contains(buyer) {
+                return Err(Error::NotOnBuyersList);
+            }
+
+            if self.held_proceeds.contains(buyer) {
+                return Err(Error::ProceedsHeld);
+            }
+
+            if self.deposits.contains(buyer) {
+                return Err(Error::DepositsHeld);
+            }
+
+            self.buyers.remove(buyer);
+
+            if let Some(rating) = self.ratings.get(buyer) {
+                self.ratings.remove(buyer);
+                self.rating_sum -= u64::from(rating);
+                self.rating_count -= 1;
+            }
+
+            if self.redelivery_requests.contains(buyer) {
+                self.redelivery_requests.remove(buyer);
+                self.redelivery_index.retain(|account| *account != buyer);
+            }
+
+            self.clear_delivery_chunks(buyer);
+
+            if let Some(position) = self
+                .buyers_index
+                .iter()
+                .position(|account| *account == buyer)
+            {
+                self.buyers_index.remove(position);
+                self.buyers_count -= 1;
+            }
+
+            return Ok(String::from("Buyer revoked"));
+        }
+
+        /// Owner side lookup of a buyer's delivery record, for support requests.
+        #[ink(message)]
+        pub fn get_buyer_delivery(&self, buyer: AccountId) -> ClientResult<DistributedStorageInfo> {
+            self.ensure_admin()?;
+
+            match self.buyers.get(buyer) {
+                None => return Err(Error::NotOnBuyersList),
+                Some(data) => return Ok(data),
+            }
+        }
+
+        /// Audit helper, `total_escrowed` being unsigned it can never go negative.
+        #[cfg(feature = "testing")]
+        #[ink(message)]
+        pub fn check_invariants(&self) -> bool {
+            let buyers_match = self.buyers_count as usize == self.buyers_index.len();
+            let pending_match = self.pending_count as usize == self.pending_index.len();
+            let pending_escrow: Balance = self
+                .pending_index
+                .iter()
+                .map(|account| self.escrowed_amount(*account))
+                .sum();
+            let escrow_match = pending_escrow == self.total_escrowed;
+            let escrow_covered = self.env().balance() >= self.total_escrowed;
+
+            return buyers_match && pending_match && escrow_match && escrow_covered;
+        }
+
+        /// Retires the contract once no intention is pending and every deposit has been
+        /// released, paying out the owner's proceeds to the payout address. Payees can
+        /// still withdraw afterwards.
+        #[ink(message)]
+        pub fn decommission(&mut self) -> ClientResult<String> {
+            self.ensure_active()?;
+            self.ensure_owner()?;
+
+            if self.pending_count > 0 {
+                return Err(Error::PendingIntentionsExist);
+            }
+
+            if self.total_held > 0 {
+                return Err(Error::ProceedsHeld);
+            }
+
+            if self.total_deposits > 0 {
+                return Err(Error::DepositsHeld);
+            }
+
+            let owner_funds = self.get_pending_withdrawal(self.payout_address);
+
+            if owner_funds > 0 {
+                self.ensure_escrow_covered(owner_funds)?;
+                self.pending_withdrawals.remove(self.payout_address);
+
+                if self
+                    .env()
+                    .transfer(self.payout_address, owner_funds)
+                    .is_err()
+                {
+                    return Err(Error::TransferError);
+                }
+
+                self.total_pending_withdrawals -= owner_funds;
+            }
+
+            self.decommissioned = true;
+
+            return Ok(String::from("Contract decommissioned"));
+        }
+
+        /// Permissionless cleanup refunding up to `max` expired intentions, returns how
+        /// many were swept.
+        #[ink(message)]
+        pub fn sweep_expired(&mut self, max: u32) -> ClientResult<u32> {
+            self.ensure_active()?;
+
+            let expired: Vec<AccountId> = self
+                .pending_index
+                .iter()
+                .take(MAX_PENDING_SCAN)
+                .filter(|account| self.is_intention_expired(**account))
+                .take(max as usize)
+                .copied()
+                .collect();
+
+            for buyer in expired.iter() {
+                let escrow = self.refund_intention(*buyer)?;
+
+                self.env().emit_event(BuyIntentionCancelled {
+                    buyer: *buyer,
+                    refunded: escrow,
+                });
+            }
+
+            return Ok(expired.len() as u32);
+        }
+
+        #[ink(message)]
+        pub fn withdraw(&mut self) -> ClientResult<String> {
+            self.ensure_not_paused()?;
+
+            let caller = self.env().caller();
+            let amount = self.get_pending_withdrawal(caller);
+
+            if amount == 0 {
+                return Err(Error::NothingToWithdraw);
+            }
+
+            self.ensure_escrow_covered(amount)?;
+
+            // Zeroed before transferring so a reentrant call finds nothing to withdraw
+            self.pending_withdrawals.remove(caller);
+
+            if self.env().transfer(caller, amount).is_err() {
+                self.pending_withdrawals.insert(caller, &amount);
+                return Err(Error::TransferError);
+            }
+
+            self.total_pending_withdrawals -= amount;
+
+            self.env().emit_event(Withdrawal {
+                account: caller,
+                amount,
+            });
+
+            return Ok(String::from("Withdrawal completed"));
+        }
+
+        //------------------------------HELPERS------------------------------
+
+        fn is_caller_owner(&self) -> bool {
+            let caller = self.env().caller();
+            return caller == self.owner;
+        }
+
+        fn ensure_owner(&self) -> ClientResult<()> {
+            if !Self::is_caller_owner(&self) {
+                return Err(Error::CallerIsNotOwner);
+            }
+
+            return Ok(());
+        }
+
+        /// Co-owners process buyers alongside the owner, settings and funds stay owner-only.
+        fn ensure_admin(&self) -> ClientResult<()> {
+            if !Self::is_caller_owner(&self) && !self.co_owners.contains(self.env().caller()) {
+                return Err(Error::CallerIsNotOwner);
+            }
+
+            return Ok(());
+        }
+
+        fn ensure_recovery_address(&self) -> ClientResult<()> {
+            if self.recovery_address != Some(self.env().caller()) {
+                return Err(Error::NotRecoveryAddress);
+            }
+
+            return Ok(());
+        }
+
+        /// Guards shared by every buy intention path, an existing intention only
+        /// passes when it can be topped up with an installment.
+        fn ensure_can_buy(&self, buyer: AccountId) -> ClientResult<()> {
+            if self.blacklist.contains(buyer) {
+                return Err(Error::Blacklisted);
+            }
+
+            if self.paused {
+                return Err(Error::ContractPaused);
+            }
+
+            if self.intentions_paused {
+                return Err(Error::IntentionsPaused);
+            }
+
+            if self.delisted {
+                return Err(Error::SongDelisted);
+            }
+
+            if self.is_sold_out() {
+                return Err(Error::SoldOut);
+            }
+
+            if self.is_sale_ended() {
+                return Err(Error::SaleEnded);
+            }
+
+            if self.buyers.contains(buyer) {
+                return Err(Error::AlreadyBuyer);
+            }
+
+            if self.possible_buyers_keys.contains(buyer) {
+                if !self.installments_enabled {
+                    return Err(Error::AlreadyOnList);
+                }
+            } else if self
+                .max_pending
+                .is_some_and(|max_pending| self.pending_count >= max_pending)
+            {
+                return Err(Error::PendingLimitReached);
+            }
+
+            return Ok(());
+        }
+
+        fn record_intention(
+            &mut self,
+            buyer: AccountId,
+            buyer_public_key: String,
+            discount_bps: u16,
+        ) -> ClientResult<String> {
+            self.ensure_can_buy(buyer)?;
+
+            if self.possible_buyers_keys.contains(buyer) {
+                return self.pay_installment(buyer);
+            }
+
+            let deposit = self.deposit_amount;
+            let minimum_payment = if self.installments_enabled {
+                1
+            } else {
+                Self::apply_discount(self.effective_price(buyer), discount_bps)
+            };
+
+            if self.env().transferred_value() < minimum_payment + deposit {
+                return Err(Error::InsufficientBalance);
+            }
+
+            // The deposit is held apart, only the rest counts towards the price
+            let mut paid = self.env().transferred_value() - deposit;
+
+            // Installments top up towards the price, so there's no surplus to refund
+            if self.refund_overpayments && !self.installments_enabled && paid > minimum_payment {
+                let surplus = paid - minimum_payment;
+
+                if self.env().transfer(self.env().caller(), surplus).is_err() {
+                    return Err(Error::TransferError);
+                }
+
+                self.last_overpayments.insert(self.env().caller(), &surplus);
+                paid = minimum_payment;
+            }
+
+            if deposit > 0 {
+                self.deposits.insert(buyer, &deposit);
+                self.depositors.insert(buyer, &self.env().caller());
+                self.total_deposits += deposit;
+            }
+
+            let key_len = buyer_public_key.len() as u32;
+
+            self.possible_buyers_keys.insert(
+                buyer,
+                &BuyerPublicKey {
+                    key: buyer_public_key,
+                    paid_so_far: paid,
+                    discount_bps,
+                    posted_at: self.env().block_timestamp(),
+                    payer: self.env().caller(),
+                },
+            );
+            self.pending_index.push(buyer);
+            self.pending_count += 1;
+            self.increase_escrow(buyer, paid);
+
+            self.env().emit_event(SongBuyIntent {
+                from: buyer,
+                owner: self.owner,
+                song_address: self.env().account_id(),
+                key_len,
+            });
+
+            if self.installments_enabled {
+                self.env().emit_event(InstallmentPaid {
+                    buyer,
+                    amount: paid,
+                    paid_so_far: paid,
+                });
+            }
+
+            return Ok(String::from("Buy intention posted"));
+        }
+
+        /// Pays out `amount` and writes the buyer's delivery, shared by approvals and
+        /// direct sales. Any failure is returned before the sale is recorded.
+        fn complete_sale(
+            &mut self,
+            buyer: AccountId,
+            amount: Balance,
+            key: String,
+            location: String,
+            mirror_location: Option<String>,
+            encryption_scheme: String,
+            note: Option<String>,
+        ) -> ClientResult<()> {
+            if !SUPPORTED_ENCRYPTION_SCHEMES.contains(&encryption_scheme.as_str()) {
+                return Err(Error::UnsupportedScheme);
+            }
+
+            let is_new_buyer = !self.buyers.contains(buyer);
+
+            if is_new_buyer && self.is_sold_out() {
+                return Err(Error::SoldOut);
+            }
+
+            let fee = self.platform_fee(amount);
+
+            if self.receipt_required {
+                let held = self.held_proceeds.get(buyer).unwrap_or(0);
+                self.held_proceeds.insert(buyer, &(held + amount - fee));
+                self.total_held += amount - fee;
+            } else if self.payees.is_empty() {
+                if self
+                    .env()
+                    .transfer(self.payout_address, amount - fee)
+                    .is_err()
+                {
+                    return Err(Error::TransferError);
+                }
+
+                self.consecutive_transfer_failures = 0;
+            } else {
+                self.credit_payees(amount - fee);
+            }
+
+            if fee > 0 && self.env().transfer(self.fee_recipient, fee).is_err() {
+                return Err(Error::TransferError);
+            }
+
+            self.total_revenue += amount;
+
+            let delivery_commitment = self.delivery_commitment(&key, &location);
+
+            let new_saved_entry = DistributedStorageInfo {
+                location,
+                mirror_location,
+                key,
+                note,
+                purchased_at: self.env().block_timestamp(),
+                license_terms_hash: self.license_terms_hash,
+                license_version: self.license_version,
+                expires_at: self
+                    .rental_duration
+                    .map(|duration| self.env().block_timestamp().saturating_add(duration)),
+                delivery_commitment,
+                encryption_scheme,
+            };
+
+            self.buyers.insert(buyer, &new_saved_entry);
+
+            if is_new_buyer {
+                self.buyers_index.push(buyer);
+                self.buyers_count += 1;
+            }
+
+            if !self.past_buyers.contains(buyer) {
+                self.past_buyers.insert(buyer, &());
+                self.lifetime_buyers += 1;
+            }
+
+            self.env().emit_event(SongBuyConfirmation {
+                buyer,
+                author: self.song_info.artist_name.clone(),
+                song_address: self.env().account_id(),
+            });
+
+            if is_new_buyer && self.is_sold_out() {
+                self.env().emit_event(SongDelisted {
+                    song_address: self.env().account_id(),
+                    reason: DelistReason::SoldOut,
+                });
+            }
+
+            self.env().emit_event(SaleCompleted {
+                buyer,
+                price_paid: amount,
+                net_to_owner: amount - fee,
+                fee,
+                song_address: self.env().account_id(),
+            });
+
+            return Ok(());
+        }
+
+        fn verify_key_ownership(
+            &self,
+            account: AccountId,
+            public_key: &str,
+            proof: &[u8; 65],
+        ) -> ClientResult<()> {
+            let expected_key =
+                Self::decode_public_key(public_key).ok_or(Error::KeyOwnershipProofInvalid)?;
+            let message_hash = self.env().hash_bytes::<Blake2x256>(account.as_ref());
+
+            match self.env().ecdsa_recover(proof, &message_hash) {
+                Ok(recovered_key) if recovered_key == expected_key => return Ok(()),
+                _ => return Err(Error::KeyOwnershipProofInvalid),
+            }
+        }
+
+        fn decode_public_key(public_key: &str) -> Option<[u8; 33]> {
+            let hex = public_key
+                .strip_prefix("0x")
+                .unwrap_or(public_key)
+                .as_bytes();
+
+            if hex.len() != 66 {
+                return None;
+            }
+
+            let mut key = [0u8; 33];
+
+            for (byte, pair) in key.iter_mut().zip(hex.chunks(2)) {
+                let high = char::from(pair[0]).to_digit(16)?;
+                let low = char::from(pair[1]).to_digit(16)?;
+                *byte = (high * 16 + low) as u8;
+            }
+
+            return Some(key);
+        }
+
+        fn pay_installment(&mut self, buyer: AccountId) -> ClientResult<String> {
+            let amount = self.env().transferred_value();
+
+            if amount == 0 {
+                return Err(Error::InsufficientBalance);
+            }
+
+            let mut intention = match self.possible_buyers_keys.get(buyer) {
+                None => return Err(Error::NotOnPossibleBuyersList),
+                Some(intention) => intention,
+            };
+
+            // Refunds go to the payer, so nobody else may add to their escrow
+            if self.env().caller() != intention.payer {
+                return Err(Error::NotPayer);
+            }
+
+            intention.paid_so_far += amount;
+            self.possible_buyers_keys.insert(buyer, &intention);
+            self.increase_escrow(buyer, amount);
+
+            self.env().emit_event(InstallmentPaid {
+                buyer,
+                amount,
+                paid_so_far: intention.paid_so_far,
+            });
+
+            return Ok(String::from("Installment paid"));
+        }
+
+        /// Accepts "mm:ss" (seconds below 60) or a plain number of seconds.
+        fn validate_duration(duration: &str) -> ClientResult<()> {
+            let is_number =
+                |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+
+            let valid = match duration.split_once(':') {
+                None => is_number(duration),
+                Some((minutes, seconds)) => {
+                    is_number(minutes) && seconds.len() == 2 && is_number(seconds) && seconds < "60"
+                }
+            };
+
+            if !valid {
+                return Err(Error::InvalidDuration);
+            }
+
+            return Ok(());
+        }
+
+        /// Lowercases tags and drops repeats, keeping the first occurrence's position.
+        fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+            let mut normalized: Vec<String> = Vec::new();
+            for tag in tags {
+                let tag = tag.to_lowercase();
+                if !normalized.contains(&tag) {
+                    normalized.push(tag);
+                }
+            }
+
+            return normalized;
+        }
+
+        fn ensure_active(&self) -> ClientResult<()> {
+            if self.decommissioned {
+                return Err(Error::Decommissioned);
+            }
+
+            return Ok(());
+        }
+
+        fn ensure_not_paused(&self) -> ClientResult<()> {
+            if self.paused {
+                return Err(Error::ContractPaused);
+            }
+
+            return Ok(());
+        }
+
+        /// Removes a pending intention and sends its escrow back to whoever paid it.
+        fn refund_intention(&mut self, buyer: AccountId) -> ClientResult<Balance> {
+            let payer = match self.possible_buyers_keys.get(buyer) {
+                None => return Err(Error::NotOnPossibleBuyersList),
+                Some(intention) => intention.payer,
+            };
+
+            let escrow = self.escrowed_amount(buyer);
+            let deposit = self.take_deposit(buyer);
+
+            self.remove_pending(buyer);
+            self.decrease_escrow(buyer, escrow);
+
+            if escrow + deposit > 0 && self.env().transfer(payer, escrow + deposit).is_err() {
+                return Err(Error::TransferError);
+            }
+
+            return Ok(escrow);
+        }
+
+        fn clear_delivery_chunks(&mut self, buyer: AccountId) {
+            if let Some(chunk_count) = self.delivery_chunk_counts.get(buyer) {
+                for index in 0..chunk_count {
+                    self.delivery_chunks.remove((buyer, index));
+                }
+                self.delivery_chunk_counts.remove(buyer);
+            }
+        }
+
+        fn take_deposit(&mut self, account: AccountId) -> Balance {
+            let deposit = self.deposits.get(account).unwrap_or(0);
+
+            if deposit > 0 {
+                self.deposits.remove(account);
+                self.depositors.remove(account);
+                self.total_deposits -= deposit;
+            }
+
+            return deposit;
+        }
+
+        fn increase_escrow(&mut self, account: AccountId, amount: Balance) {
+            self.total_escrowed += amount;
+
+            self.env().emit_event(EscrowChanged {
+                account,
+                delta: amount,
+                is_increase: true,
+                total_escrowed: self.total_escrowed,
+            });
+        }
+
+        fn decrease_escrow(&mut self, account: AccountId, amount: Balance) {
+            self.total_escrowed -= amount;
+
+            self.env().emit_event(EscrowChanged {
+                account,
+                delta: amount,
+                is_increase: false,
+                total_escrowed: self.total_escrowed,
+            });
+        }
+
+        fn remove_pending(&mut self, buyer: AccountId) {
+            self.possible_buyers_keys.remove(buyer);
+
+            if let Some(position) = self
+                .pending_index
+                .iter()
+                .position(|account| *account == buyer)
+            {
+                self.pending_index.swap_remove(position);
+                self.pending_count -= 1;
+            }
+        }
+
+        /// Rounding leftovers are credited to the payout address.
+        fn credit_payees(&mut self, amount: Balance) {
+            let mut credited: Balance = 0;
+
+            for (payee, share) in self.payees.iter() {
+                let payee_amount = amount.saturating_mul(Balance::from(*share)) / 10_000;
+                let pending = self.pending_withdrawals.get(payee).unwrap_or(0);
+
+                self.pending_withdrawals
+                    .insert(payee, &(pending + payee_amount));
+                credited += payee_amount;
+            }
+
+            if amount > credited {
+                let pending = self.get_pending_withdrawal(self.payout_address);
+                self.pending_withdrawals
+                    .insert(self.payout_address, &(pending + amount - credited));
+            }
+
+            self.total_pending_withdrawals += amount;
+        }
+
+        /// Escrow, held proceeds and deposits are never used to pay anything else, so the
+        /// balance left after paying out `amount` must still cover them.
+        fn ensure_escrow_covered(&self, amount: Balance) -> ClientResult<()> {
+            if self.env().balance().saturating_sub(amount)
+                < self.total_escrowed + self.total_held + self.total_deposits
+            {
+                return Err(Error::WouldBreakEscrow);
+            }
+
+            return Ok(());
+        }
+
+        fn delivery_commitment(&self, key: &str, location: &str) -> [u8; 32] {
+            return self.env().hash_encoded::<Blake2x256, _>(&(key, location));
+        }
+
+        fn is_access_expired(&self, delivery: &DistributedStorageInfo) -> bool {
+            return delivery
+                .expires_at
+                .is_some_and(|expires_at| self.env().block_timestamp() >= expires_at);
+        }
+
+        fn is_intention_expired(&self, buyer: AccountId) -> bool {
+            let Some(intention_ttl) = self.intention_ttl else {
+                return false;
+            };
+
+            return self
+                .possible_buyers_keys
+                .get(buyer)
+                .is_some_and(|intention| {
+                    self.env().block_timestamp()
+                        >= intention.posted_at.saturating_add(intention_ttl)
+                });
+        }
+
+        fn release_held_proceeds(&mut self, buyer: AccountId) -> ClientResult<()> {
+            let held = self.held_proceeds.get(buyer).unwrap_or(0);
+
+            if held == 0 {
+                return Err(Error::NothingToWithdraw);
+            }
+
+            self.held_proceeds.remove(buyer);
+            self.total_held -= held;
+
+            if self.payees.is_empty() {
+                let pending = self.get_pending_withdrawal(self.payout_address);
+                self.pending_withdrawals
+                    .insert(self.payout_address, &(pending + held));
+                self.total_pending_withdrawals += held;
+            } else {
+                self.credit_payees(held);
+            }
+
+            return Ok(());
+        }
+
+        fn escrowed_amount(&self, buyer: AccountId) -> Balance {
+            return self
+                .possible_buyers_keys
+                .get(buyer)
+                .map_or(0, |intention| intention.paid_so_far);
+        }
+
+        fn effective_price(&self, buyer: AccountId) -> Balance {
+            return self.custom_prices.get(buyer).unwrap_or(self.price);
+        }
+
+        /// Effective price minus the discount the buyer's intention was posted with.
+        fn amount_due(&self, buyer: AccountId) -> Balance {
+            let discount_bps = self
+                .possible_buyers_keys
+                .get(buyer)
+                .map_or(0, |intention| intention.discount_bps);
+
+            return Self::apply_discount(self.effective_price(buyer), discount_bps);
+        }
+
+        fn apply_discount(price: Balance, discount_bps: u16) -> Balance {
+            return price - price.saturating_mul(Balance::from(discount_bps)) / 10_000;
+        }
+
+        fn hash_code(&self, code: &str) -> Hash {
+            return Hash::from(self.env().hash_bytes::<Blake2x256>(code.as_bytes()));
+        }
+
+        fn is_sold_out(&self) -> bool {
+            return self
+                .max_editions
+                .is_some_and(|max_editions| self.buyers_count >= max_editions);
+        }
+
+        fn is_sale_ended(&self) -> bool {
+            return self
+                .sale_ends_at
+                .is_some_and(|sale_ends_at| self.env().block_timestamp() >= sale_ends_at);
+        }
+
+        fn record_transfer_failure(&mut self) {
+            self.consecutive_transfer_failures += 1;
+
+            if self.consecutive_transfer_failures >= AUTO_PAUSE_TRANSFER_FAILURES && !self.paused {
+                self.paused = true;
+                self.pause_reason = Some(String::from("Owner payouts keep failing"));
+
+                self.env().emit_event(AutoPaused {
+                    failures: self.consecutive_transfer_failures,
+                });
+            }
+        }
+
+        fn platform_fee(&self, amount: Balance) -> Balance {
+            let fee_bps = Balance::from(self.fee_bps);
+
+            // Splitting the amount keeps the exact fee in range when the product overflows.
+            return match amount.checked_mul(fee_bps) {
+                Some(product) => product / 10_000,
+                None => amount / 10_000 * fee_bps + amount % 10_000 * fee_bps / 10_000,
+            };
+        }
+    }
+
+    //------------------------------TESTS------------------------------
+
+    #[cfg(test)]
+    mod tests {
+        use ink::primitives::AccountId;
+
+        use super::*;
+
+        // We define some helper Accounts to make our tests more readable
+        fn default_accounts() -> ink::env::test::DefaultAccounts<Environment> {
+            ink::env::test::default_accounts::<Environment>()
+        }
+
+        fn alice() -> AccountId {
+            default_accounts().alice
+        }
+
+        fn bob() -> AccountId {
+            default_accounts().bob
+        }
+
+        fn charlie() -> AccountId {
+            default_accounts().charlie
+        }
+
+        fn django() -> AccountId {
+            default_accounts().django
+        }
+
+        #[ink::test]
+        fn publish_works() {
+            let contract = publish();
+
+            let song = contract.get_song_info();
+
+            assert_eq!(song.price, 10);
+            assert_eq!(song.song_info.song_name, "La bebe - ringtone");
+            assert_eq!(song.song_info.artist_name, "Peso Pluma");
+            assert_eq!(
+                song.song_info.watermark_image_ipfs,
+                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o"
+            );
+        }
+
+        const IMAGE_HASH: [u8; 32] = [7; 32];
+        const DECIMALS: u8 = 12;
+        const LICENSE_TERMS_HASH: [u8; 32] = [3; 32];
+        const ENCRYPTION_SCHEME: &str = "AES-256-GCM+ECIES";
+
+        // Compressed secp256k1 key shared by every test buyer, the proofs below are
+        // its signatures over the blake2 hash of each default account.
+        const BUYER_PUBLIC_KEY: &str =
+            "02085fe2ca7a5758957ea811bd8e743d9cee6bc20072f1470a888c43a1091a8e8b";
+        const BOB_KEY_PROOF: [u8; 65] = [
+            231, 207, 199, 52, 122, 185, 113, 252, 8, 128, 41, 214, 169, 168, 199, 39, 128, 3, 237,
+            243, 110, 207, 51, 214, 166, 233, 134, 180, 4, 30, 241, 245, 106, 146, 17, 162, 171,
+            145, 240, 221, 27, 135, 214, 173, 233, 22, 20, 6, 91, 83, 196, 119, 202, 156, 86, 161,
+            110, 51, 54, 85, 97, 67, 22, 99, 0,
+        ];
+        const CHARLIE_KEY_PROOF: [u8; 65] = [
+            108, 210, 142, 80, 115, 180, 23, 233, 114, 4, 100, 184, 80, 199, 243, 65, 165, 184, 29,
+            70, 16, 251, 168, 249, 18, 70, 155, 142, 184, 104, 63, 214, 123, 68, 31, 27, 185, 35,
+            154, 197, 240, 7, 62, 0, 75, 84, 125, 55, 57, 141, 32, 184, 60, 53, 82, 232, 232, 46,
+            76, 115, 221, 202, 46, 254, 0,
+        ];
+        const DJANGO_KEY_PROOF: [u8; 65] = [
+            236, 146, 77, 234, 249, 168, 243, 212, 250, 151, 0, 161, 198, 93, 71, 230, 21, 92, 50,
+            45, 42, 123, 173, 176, 153, 224, 29, 142, 83, 141, 89, 98, 72, 96, 224, 42, 242, 18,
+            163, 219, 201, 127, 224, 159, 98, 131, 230, 216, 247, 252, 189, 219, 114, 251, 12, 225,
+            187, 123, 120, 202, 136, 116, 1, 37, 1,
+        ];
+        const EVE_KEY_PROOF: [u8; 65] = [
+            25, 134, 162, 41, 76, 87, 67, 161, 130, 147, 4, 231, 64, 168, 235, 52, 177, 145, 101,
+            137, 38, 97, 221, 3, 185, 88, 45, 20, 52, 69, 235, 12, 66, 211, 185, 99, 106, 184, 121,
+            247, 155, 87, 157, 19, 181, 40, 196, 83, 233, 112, 159, 110, 113, 147, 32, 59, 28, 178,
+            201, 63, 73, 250, 5, 1, 1,
+        ];
+        const FRANK_KEY_PROOF: [u8; 65] = [
+            37, 169, 229, 45, 214, 103, 8, 54, 221, 55, 133, 140, 171, 64, 247, 250, 122, 157, 83,
+            205, 131, 11, 61, 35, 54, 81, 32, 176, 126, 62, 234, 202, 31, 203, 211, 221, 163, 54,
+            45, 51, 110, 205, 121, 216, 99, 151, 77, 163, 95, 159, 99, 230, 218, 35, 212, 137, 153,
+            247, 79, 116, 82, 200, 99, 188, 0,
+        ];
+
+        fn key_proof(account: AccountId) -> [u8; 65] {
+            if account == bob() {
+                BOB_KEY_PROOF
+            } else if account == charlie() {
+                CHARLIE_KEY_PROOF
+            } else if account == django() {
+                DJANGO_KEY_PROOF
+            } else if account == default_accounts().eve {
+                EVE_KEY_PROOF
+            } else {
+                FRANK_KEY_PROOF
+            }
+        }
+
+        fn publish() -> ContractPublish {
+            publish_with(None, Vec::new())
+        }
+
+        fn publish_with(max_pending: Option<u32>, tags: Vec<String>) -> ContractPublish {
+            ContractPublish::publish_song(
+                "La bebe - ringtone".to_string(),
+                10,
+                "Peso Pluma".to_string(),
+                "3:45".to_string(),
+                "Genesis".to_string(),
+                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o".to_string(),
+                IMAGE_HASH,
+                max_pending,
+                tags,
+                DECIMALS,
+                LICENSE_TERMS_HASH,
+                0,
+            )
+        }
+
+        fn post_intention_as(
+            contract: &mut ContractPublish,
+            buyer: AccountId,
+            value: Balance,
+        ) -> ClientResult<String> {
+            // Off-chain, django and the accounts after him start without funds
+            if ink::env::test::get_account_balance::<Environment>(buyer).unwrap_or(0) < value {
+                ink::env::test::set_account_balance::<Environment>(buyer, value + 1_000_000);
+            }
+            ink::env::test::set_caller::<Environment>(buyer);
+            ink::env::test::transfer_in::<Environment>(value);
+            contract.post_buy_intention(BUYER_PUBLIC_KEY.to_string(), key_proof(buyer))
+        }
+
+        fn approve_as_owner(
+            contract: &mut ContractPublish,
+            buyer: AccountId,
+        ) -> ClientResult<String> {
+            ink::env::test::set_caller::<Environment>(alice());
+            contract.set_new_allowed_buyer(
+                "encrypted-symmetric-key".to_string(),
+                "QmSongAddress".to_string(),
+                None,
+                ENCRYPTION_SCHEME.to_string(),
+                buyer,
+                None,
+                None,
+            )
+        }
+
+        #[ink::test]
+        fn get_config_matches_constructed_values() {
+            let contract = publish();
+
+            let config = contract.get_config();
+
+            assert_eq!(config.price, 10);
+            assert_eq!(config.owner, alice());
+            assert!(!config.paused);
+            assert_eq!(config.max_editions, None);
+            assert_eq!(config.fee_bps, 0);
+            assert_eq!(config.buyers_count, 0);
+        }
+
+        #[ink::test]
+        fn validate_duration_accepts_minutes_and_seconds() {
+            assert_eq!(ContractPublish::validate_duration("3:45"), Ok(()));
+        }
+
+        #[ink::test]
+        fn validate_duration_accepts_plain_seconds() {
+            assert_eq!(ContractPublish::validate_duration("225"), Ok(()));
+        }
+
+        #[ink::test]
+        fn validate_duration_rejects_invalid_value() {
+            assert_eq!(
+                ContractPublish::validate_duration("abc"),
+                Err(Error::InvalidDuration)
+            );
+        }
+
+        #[ink::test]
+        fn publish_song_checked_rejects_invalid_duration() {
+            let result = ContractPublish::publish_song_checked(
+                "La bebe - ringtone".to_string(),
+                10,
+                "Peso Pluma".to_string(),
+                "banana".to_string(),
+                "Genesis".to_string(),
+                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o".to_string(),
+                IMAGE_HASH,
+                None,
+                Vec::new(),
+                DECIMALS,
+                LICENSE_TERMS_HASH,
+                0,
+            );
+
+            assert!(matches!(result, Err(Error::InvalidDuration)));
+        }
+
+        #[ink::test]
+        fn publish_song_checked_rejects_zero_price() {
+            let result = ContractPublish::publish_song_checked(
+                "La bebe - ringtone".to_string(),
+                0,
+                "Peso Pluma".to_string(),
+                "3:45".to_string(),
+                "Genesis".to_string(),
+                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o".to_string(),
+                IMAGE_HASH,
+                None,
+                Vec::new(),
+                DECIMALS,
+                LICENSE_TERMS_HASH,
+                0,
+            );
+
+            assert!(matches!(result, Err(Error::ZeroPrice)));
+        }
+
+        #[ink::test]
+        #[should_panic(expected = "song_price must be above zero")]
+        fn publish_song_panics_on_zero_price() {
+            ContractPublish::publish_song(
+                "La bebe - ringtone".to_string(),
+                0,
+                "Peso Pluma".to_string(),
+                "3:45".to_string(),
+                "Genesis".to_string(),
+                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o".to_string(),
+                IMAGE_HASH,
+                None,
+                Vec::new(),
+                DECIMALS,
+                LICENSE_TERMS_HASH,
+                0,
+            );
+        }
+
+        #[ink::test]
+        fn gifted_intention_makes_recipient_the_buyer() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(10);
+            assert!(contract
+                .post_buy_intention_for(charlie(), BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF)
+                .is_ok());
+
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            assert!(contract.buyers.contains(charlie()));
+            assert!(!contract.buyers.contains(bob()));
+        }
+
+        #[ink::test]
+        fn gift_to_owner_is_rejected() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(10);
+
+            assert_eq!(
+                contract.post_buy_intention_for(
+                    alice(),
+                    BUYER_PUBLIC_KEY.to_string(),
+                    BOB_KEY_PROOF
+                ),
+                Err(Error::RecipientIsOwner)
+            );
+        }
+
+        #[ink::test]
+        fn repeated_transfer_failures_auto_pause() {
+            // The off-chain engine panics instead of failing a transfer the contract
+            // can't afford, so failures are reported the way the owner's tooling does.
+            let mut contract = publish();
+
+            for _ in 0..AUTO_PAUSE_TRANSFER_FAILURES {
+                assert!(contract.report_payout_failure().is_ok());
+            }
+
+            assert!(contract.paused);
+
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let auto_paused =
+                <AutoPaused as scale::Decode>::decode(&mut &events.last().unwrap().data[..])
+                    .unwrap();
+            assert_eq!(auto_paused.failures, AUTO_PAUSE_TRANSFER_FAILURES);
+
+            assert_eq!(
+                post_intention_as(&mut contract, charlie(), 10),
+                Err(Error::ContractPaused)
+            );
+        }
+
+        #[ink::test]
+        fn successful_transfer_resets_failure_counter() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.report_payout_failure().is_ok());
+            assert_eq!(contract.consecutive_transfer_failures, 1);
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(contract.consecutive_transfer_failures, 0);
+            assert!(!contract.paused);
+            assert!(contract.buyers.contains(bob()));
+        }
+
+        #[cfg(feature = "testing")]
+        #[ink::test]
+        fn invariants_hold_through_buy_flow() {
+            let mut contract = publish();
+            assert!(contract.check_invariants());
+
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(contract.check_invariants());
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(contract.check_invariants());
+        }
+
+        #[cfg(feature = "testing")]
+        #[ink::test]
+        fn invariants_detect_buyers_count_mismatch() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            contract.buyers_count += 1;
+
+            assert!(!contract.check_invariants());
+        }
+
+        #[cfg(feature = "testing")]
+        #[ink::test]
+        fn invariants_detect_escrow_mismatch() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            contract.total_escrowed = 5;
+
+            assert!(!contract.check_invariants());
+        }
+
+        #[ink::test]
+        fn royalty_info_returns_owner_cut() {
+            let mut contract = publish();
+            assert!(contract.set_resale_royalty(750).is_ok());
+
+            assert_eq!(contract.royalty_info(2_000), (alice(), 150));
+        }
+
+        #[ink::test]
+        fn buyer_note_round_trips() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract
+                .set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    None,
+                    ENCRYPTION_SCHEME.to_string(),
+                    bob(),
+                    Some("License: CC BY-NC 4.0".to_string()),
+                    None,
+                )
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().ok().unwrap();
+            assert_eq!(delivery.note, Some("License: CC BY-NC 4.0".to_string()));
+        }
+
+        #[ink::test]
+        fn over_length_note_is_rejected() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            let result = contract.set_new_allowed_buyer(
+                "encrypted-symmetric-key".to_string(),
+                "QmSongAddress".to_string(),
+                None,
+                ENCRYPTION_SCHEME.to_string(),
+                bob(),
+                Some("a".repeat(MAX_NOTE_LEN + 1)),
+                None,
+            );
+
+            assert_eq!(result, Err(Error::NoteTooLong));
+            assert!(contract.possible_buyers_keys.contains(bob()));
+        }
+
+        fn last_delist_reason() -> DelistReason {
+            emitted_events::<SongDelisted>().pop().unwrap().reason
+        }
+
+        #[ink::test]
+        fn manual_delist_emits_manual_reason() {
+            let mut contract = publish();
+
+            assert!(contract.delist_song().is_ok());
+
+            assert_eq!(last_delist_reason(), DelistReason::Manual);
+            assert_eq!(
+                post_intention_as(&mut contract, bob(), 10),
+                Err(Error::SongDelisted)
+            );
+        }
+
+        #[ink::test]
+        fn reaching_edition_cap_emits_sold_out_reason() {
+            let mut contract = publish();
+            assert!(contract.set_max_editions(Some(1)).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(last_delist_reason(), DelistReason::SoldOut);
+            assert_eq!(
+                post_intention_as(&mut contract, charlie(), 10),
+                Err(Error::SoldOut)
+            );
+        }
+
+        #[ink::test]
+        fn set_price_works_until_locked() {
+            let mut contract = publish();
+
+            assert!(contract.set_price(20).is_ok());
+            assert_eq!(contract.get_song_info().price, 20);
+
+            assert!(contract.lock_price().is_ok());
+            assert!(contract.is_price_locked());
+
+            assert_eq!(contract.set_price(30), Err(Error::PriceLocked));
+            assert_eq!(contract.get_song_info().price, 20);
+        }
+
+        #[ink::test]
+        fn image_content_hash_round_trips() {
+            let contract = publish();
+
+            assert_eq!(
+                contract.get_song_info().song_info.image_content_hash,
+                IMAGE_HASH
+            );
+        }
+
+        #[ink::test]
+        fn underfunded_buyers_lists_only_short_escrows() {
+            let mut contract = publish();
+            assert!(contract.set_installments(true).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 4).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+
+            assert_eq!(contract.get_underfunded_buyers(), vec![bob()]);
+        }
+
+        #[ink::test]
+        fn extra_metadata_round_trips() {
+            let mut contract = publish();
+            assert!(contract.get_song_info().song_info.extra_metadata.is_empty());
+
+            assert!(contract.set_extra_metadata(vec![1, 2, 3]).is_ok());
+
+            assert_eq!(
+                contract.get_song_info().song_info.extra_metadata,
+                vec![1, 2, 3]
+            );
+        }
+
+        #[ink::test]
+        fn oversized_extra_metadata_is_rejected() {
+            let mut contract = publish();
+
+            assert_eq!(
+                contract.set_extra_metadata(vec![0; MAX_EXTRA_METADATA_LEN + 1]),
+                Err(Error::MetadataTooLarge)
+            );
+        }
+
+        #[ink::test]
+        fn purchase_timestamp_is_recorded_at_approval() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_block_timestamp::<Environment>(1_700_000_000_000);
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().unwrap();
+            assert_eq!(delivery.purchased_at, 1_700_000_000_000);
+        }
+
+        #[ink::test]
+        fn sale_with_splits_credits_payees() {
+            let mut contract = publish();
+            assert!(contract
+                .set_payees(vec![(charlie(), 7_000), (django(), 3_000)])
+                .is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(contract.get_pending_withdrawal(charlie()), 7);
+            assert_eq!(contract.get_pending_withdrawal(django()), 3);
+            assert_eq!(contract.get_pending_withdrawal(alice()), 0);
+        }
+
+        #[ink::test]
+        fn payees_must_add_up_to_whole_price() {
+            let mut contract = publish();
+
+            assert_eq!(
+                contract.set_payees(vec![(charlie(), 7_000), (django(), 2_000)]),
+                Err(Error::InvalidShares)
+            );
+        }
+
+        #[ink::test]
+        fn approval_releases_original_escrow() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 12).is_ok());
+            let owner_balance =
+                ink::env::test::get_account_balance::<Environment>(alice()).unwrap();
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(alice()).unwrap(),
+                owner_balance + 12
+            );
+            assert_eq!(contract.total_escrowed, 0);
+        }
+
+        #[ink::test]
+        fn approval_without_escrow_is_rejected() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            let mut intention = contract.possible_buyers_keys.get(bob()).unwrap();
+            intention.paid_so_far = 0;
+            contract.possible_buyers_keys.insert(bob(), &intention);
+
+            assert_eq!(approve_as_owner(&mut contract, bob()), Err(Error::NoEscrow));
+        }
+
+        #[ink::test]
+        fn have_purchased_preserves_input_order() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            assert_eq!(
+                contract.have_purchased(vec![django(), bob(), alice(), charlie()]),
+                vec![false, true, false, true]
+            );
+        }
+
+        #[ink::test]
+        fn have_purchased_is_capped() {
+            let contract = publish();
+
+            let answers = contract.have_purchased(vec![bob(); MAX_BATCH_QUERY + 1]);
+
+            assert_eq!(answers.len(), MAX_BATCH_QUERY);
+        }
+
+        #[ink::test]
+        fn installments_emit_cumulative_totals() {
+            let mut contract = publish();
+            assert!(contract.set_installments(true).is_ok());
+
+            assert!(post_intention_as(&mut contract, bob(), 4).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 6).is_ok());
+
+            let installments = emitted_events::<InstallmentPaid>();
+
+            assert_eq!(installments[0].amount, 4);
+            assert_eq!(installments[0].paid_so_far, 4);
+            assert_eq!(installments[1].amount, 6);
+            assert_eq!(installments[1].paid_so_far, 10);
+            assert_eq!(contract.total_escrowed, 10);
+        }
+
+        #[ink::test]
+        fn amount_remaining_tracks_installments() {
+            let mut contract = publish();
+            assert!(contract.set_installments(true).is_ok());
+
+            assert!(post_intention_as(&mut contract, bob(), 4).is_ok());
+            assert_eq!(contract.amount_remaining(bob()), 6);
+
+            assert!(post_intention_as(&mut contract, bob(), 6).is_ok());
+            assert_eq!(contract.amount_remaining(bob()), 0);
+        }
+
+        #[ink::test]
+        fn intentions_are_rejected_after_sale_end() {
+            let mut contract = publish();
+            assert!(contract.set_sale_end(Some(1_000)).is_ok());
+            assert_eq!(contract.get_sale_end(), Some(1_000));
+
+            ink::env::test::set_block_timestamp::<Environment>(999);
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_block_timestamp::<Environment>(1_001);
+            assert_eq!(
+                post_intention_as(&mut contract, charlie(), 10),
+                Err(Error::SaleEnded)
+            );
+        }
+
+        #[ink::test]
+        fn valid_key_ownership_proof_is_accepted() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(10);
+
+            assert!(contract
+                .post_buy_intention(BUYER_PUBLIC_KEY.to_string(), BOB_KEY_PROOF)
+                .is_ok());
+        }
+
+        #[ink::test]
+        fn forged_key_ownership_proof_is_rejected() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            ink::env::test::transfer_in::<Environment>(10);
+
+            assert_eq!(
+                contract.post_buy_intention(BUYER_PUBLIC_KEY.to_string(), BOB_KEY_PROOF),
+                Err(Error::KeyOwnershipProofInvalid)
+            );
+            assert!(!contract.possible_buyers_keys.contains(charlie()));
+        }
+
+        #[ink::test]
+        fn song_name_hash_matches_blake2_digest() {
+            let contract = publish();
+
+            let expected = Hash::from([
+                27, 204, 242, 155, 174, 174, 70, 149, 0, 139, 61, 66, 203, 120, 168, 7, 22, 34, 50,
+                177, 253, 58, 66, 192, 174, 128, 44, 139, 162, 152, 166, 108,
+            ]);
+
+            assert_eq!(contract.get_song_name_hash(), expected);
+        }
+
+        #[ink::test]
+        fn decommission_blocks_mutations_but_not_getters() {
+            let mut contract = publish();
+
+            assert!(contract.decommission().is_ok());
+
+            assert_eq!(
+                post_intention_as(&mut contract, bob(), 10),
+                Err(Error::Decommissioned)
+            );
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.set_price(20), Err(Error::Decommissioned));
+            assert_eq!(
+                contract.get_song_info().song_info.song_name,
+                "La bebe - ringtone"
+            );
+        }
+
+        #[ink::test]
+        fn decommission_requires_no_pending_intentions() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.decommission(), Err(Error::PendingIntentionsExist));
+        }
+
+        #[ink::test]
+        fn audit_summary_counts_pending_and_confirmed() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, django(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            let summary = contract.audit_summary();
+
+            assert_eq!(summary.pending, 2);
+            assert_eq!(summary.confirmed, 1);
+        }
+
+        #[ink::test]
+        fn chunked_delivery_is_readable_after_finalize() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert!(contract
+                .set_delivery_chunk(bob(), 1, b"-second-half".to_vec())
+                .is_ok());
+            assert!(contract
+                .set_delivery_chunk(bob(), 0, b"first-half".to_vec())
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(matches!(
+                contract.get_address_and_key_buyer(),
+                Err(Error::DeliveryIncomplete)
+            ));
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.finalize_delivery(bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().unwrap();
+            assert_eq!(delivery.key, "first-half-second-half");
+        }
+
+        #[ink::test]
+        fn aborted_chunks_restore_the_previous_delivery() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert!(contract
+                .set_delivery_chunk(bob(), 0, b"mistaken-upload".to_vec())
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(
+                contract.abort_delivery_chunks(bob()),
+                Err(Error::CallerIsNotOwner)
+            );
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.abort_delivery_chunks(bob()).is_ok());
+            assert!(contract.delivery_chunks.get((bob(), 0)).is_none());
+            assert_eq!(
+                contract.abort_delivery_chunks(bob()),
+                Err(Error::NoChunkedDelivery)
+            );
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().unwrap();
+            assert_eq!(delivery.key, "encrypted-symmetric-key");
+        }
+
+        #[ink::test]
+        fn finalize_with_missing_chunk_is_rejected() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert!(contract
+                .set_delivery_chunk(bob(), 1, b"-second-half".to_vec())
+                .is_ok());
+
+            assert_eq!(
+                contract.finalize_delivery(bob()),
+                Err(Error::DeliveryIncomplete)
+            );
+        }
+
+        #[ink::test]
+        fn net_proceeds_account_for_fee_and_split() {
+            let mut contract = publish();
+            assert!(contract.set_price(100).is_ok());
+            assert!(contract.set_platform_fee(django(), 1_000).is_ok());
+            assert!(contract
+                .set_payees(vec![(alice(), 5_000), (charlie(), 5_000)])
+                .is_ok());
+
+            assert_eq!(contract.estimate_net_proceeds(), (alice(), 45));
+        }
+
+        #[ink::test]
+        fn net_proceeds_name_a_separate_payout_address() {
+            let mut contract = publish();
+            assert!(contract.set_price(100).is_ok());
+            assert!(contract.set_platform_fee(django(), 1_000).is_ok());
+            assert!(contract.set_payout_address(charlie()).is_ok());
+
+            assert_eq!(contract.estimate_net_proceeds(), (charlie(), 90));
+
+            assert!(contract
+                .set_payees(vec![(alice(), 3_333), (charlie(), 6_667)])
+                .is_ok());
+
+            // alice is credited 29 of the 90, charlie's 60 plus the leftover 1 are paid out
+            assert_eq!(contract.estimate_net_proceeds(), (charlie(), 61));
+        }
+
+        #[ink::test]
+        fn repeated_idempotency_key_approves_once() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            let owner_balance =
+                ink::env::test::get_account_balance::<Environment>(alice()).unwrap();
+
+            ink::env::test::set_caller::<Environment>(alice());
+            for _ in 0..2 {
+                assert!(contract
+                    .set_new_allowed_buyer(
+                        "encrypted-symmetric-key".to_string(),
+                        "QmSongAddress".to_string(),
+                        None,
+                        ENCRYPTION_SCHEME.to_string(),
+                        bob(),
+                        None,
+                        Some([9; 32]),
+                    )
+                    .is_ok());
+            }
+
+            assert_eq!(contract.buyers_count, 1);
+            assert_eq!(contract.buyers_index, vec![bob()]);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(alice()).unwrap(),
+                owner_balance + 10
+            );
+        }
+
+        #[ink::test]
+        fn rating_by_returns_stored_rating() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.rate_song(4).is_ok());
+
+            assert_eq!(contract.get_rating_by(bob()), Some(4));
+            assert_eq!(contract.get_rating_by(charlie()), None);
+            assert_eq!(contract.get_average_rating(), Some(400));
+        }
+
+        #[ink::test]
+        fn proceeds_go_to_payout_address() {
+            // The off-chain callee defaults to alice, give the contract its own account
+            ink::env::test::set_callee::<Environment>(AccountId::from([0xFF; 32]));
+            let mut contract = publish();
+            let treasury = default_accounts().eve;
+            assert!(contract.set_payout_address(treasury).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            let owner_balance =
+                ink::env::test::get_account_balance::<Environment>(alice()).unwrap();
+            let treasury_balance =
+                ink::env::test::get_account_balance::<Environment>(treasury).unwrap_or(0);
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(treasury).unwrap(),
+                treasury_balance + 10
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(alice()).unwrap(),
+                owner_balance
+            );
+            assert_eq!(contract.get_config().owner, alice());
+        }
+
+        #[ink::test]
+        fn withdrawal_emits_event_and_zeroes_balance() {
+            // Only the success path is covered, the off-chain engine can't make a transfer
+            // fail: it has no reverting recipients and panics when the contract can't pay.
+            let mut contract = publish();
+            assert!(contract
+                .set_payees(vec![(charlie(), 7_000), (django(), 3_000)])
+                .is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.withdraw().is_ok());
+
+            assert_eq!(contract.get_pending_withdrawal(charlie()), 0);
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+            let withdrawal =
+                <Withdrawal as scale::Decode>::decode(&mut &events.last().unwrap().data[..])
+                    .unwrap();
+            assert_eq!(withdrawal.account, charlie());
+            assert_eq!(withdrawal.amount, 7);
+        }
+
+        #[ink::test]
+        fn song_address_is_contract_account() {
+            let contract = publish();
+
+            assert_eq!(
+                contract.get_song_address(),
+                ink::env::test::callee::<Environment>()
+            );
+        }
+
+        #[ink::test]
+        fn pending_limit_frees_slot_on_cancellation() {
+            let mut contract = publish_with(Some(2), Vec::new());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+
+            assert_eq!(
+                post_intention_as(&mut contract, django(), 10),
+                Err(Error::PendingLimitReached)
+            );
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.cancel_buy_intention().is_ok());
+
+            assert!(post_intention_as(&mut contract, django(), 10).is_ok());
+        }
+
+        fn code_hash(code: &str) -> Hash {
+            let mut output = [0u8; 32];
+            ink::env::hash_bytes::<Blake2x256>(code.as_bytes(), &mut output);
+            Hash::from(output)
+        }
+
+        #[ink::test]
+        fn discount_code_validation() {
+            let mut contract = publish();
+            assert!(contract
+                .add_discount_code(code_hash("SUMMER25"), 2_500)
+                .is_ok());
+
+            assert!(contract.is_valid_discount_code("SUMMER25".to_string()));
+            assert!(!contract.is_valid_discount_code("WINTER25".to_string()));
+        }
+
+        #[ink::test]
+        fn free_purchases_are_rejected() {
+            let mut contract = publish();
+
+            assert_eq!(
+                contract.add_discount_code(code_hash("FREE"), 10_000),
+                Err(Error::InvalidDiscount)
+            );
+            assert!(!contract.is_valid_discount_code("FREE".to_string()));
+            assert_eq!(contract.set_price(0), Err(Error::ZeroPrice));
+        }
+
+        #[ink::test]
+        fn discount_code_lowers_required_payment() {
+            let mut contract = publish();
+            assert!(contract
+                .add_discount_code(code_hash("SUMMER25"), 2_500)
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(8);
+            assert!(contract
+                .post_buy_intention_with_code(
+                    BUYER_PUBLIC_KEY.to_string(),
+                    BOB_KEY_PROOF,
+                    "SUMMER25".to_string()
+                )
+                .is_ok());
+
+            assert_eq!(contract.amount_remaining(bob()), 0);
+        }
+
+        #[ink::test]
+        fn has_tag_checks_constructed_tags() {
+            let contract = publish_with(None, vec!["reggaeton".to_string(), "2024".to_string()]);
+
+            assert!(contract.has_tag("reggaeton".to_string()));
+            assert!(!contract.has_tag("jazz".to_string()));
+        }
+
+        #[ink::test]
+        fn set_price_is_blocked_while_intentions_are_pending() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.set_price(20), Err(Error::PendingIntentionsExist));
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(contract.set_price(20).is_ok());
+        }
+
+        #[ink::test]
+        fn buyer_state_covers_all_codes() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            assert_eq!(contract.buyer_state(django()), 0);
+            assert_eq!(contract.buyer_state(bob()), 1);
+            assert_eq!(contract.buyer_state(charlie()), 2);
+        }
+
+        #[ink::test]
+        fn interested_account_reads_preview() {
+            let mut contract = publish();
+            assert!(contract
+                .set_preview("preview-key".to_string(), "QmPreviewClip".to_string())
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(matches!(contract.get_preview(), Err(Error::NotInterested)));
+
+            assert!(contract.register_interest().is_ok());
+
+            let preview = contract.get_preview().unwrap();
+            assert_eq!(preview.location, "QmPreviewClip");
+            assert_eq!(preview.key, "preview-key");
+        }
+
+        #[ink::test]
+        fn public_delivery_opens_to_anyone_after_date() {
+            let mut contract = publish();
+            assert!(contract
+                .set_public_release(
+                    "plain-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    Some(5_000)
+                )
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(django());
+            ink::env::test::set_block_timestamp::<Environment>(4_999);
+            assert!(matches!(
+                contract.get_public_delivery(),
+                Err(Error::NotPublicYet)
+            ));
+
+            ink::env::test::set_block_timestamp::<Environment>(5_000);
+            assert_eq!(contract.get_public_delivery().unwrap().key, "plain-key");
+        }
+
+        #[ink::test]
+        fn owner_rejection_refunds_buyer() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.reject_intention(bob()).is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 10
+            );
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert_eq!(
+                contract.reject_intention(bob()),
+                Err(Error::NotOnPossibleBuyersList)
+            );
+        }
+
+        #[ink::test]
+        fn price_scaled_returns_configured_decimals() {
+            let contract = publish();
+
+            assert_eq!(contract.get_price_scaled(), (10, DECIMALS));
+        }
+
+        fn emitted_events<E: ink::env::Event + scale::Decode>() -> Vec<E> {
+            ink::env::test::recorded_events()
+                .filter(|event| {
+                    event.topics.first().map(|topic| topic.as_slice())
+                        == E::SIGNATURE_TOPIC.as_ref().map(|topic| topic.as_slice())
+                })
+                .map(|event| E::decode(&mut &event.data[..]).unwrap())
+                .collect()
+        }
+
+        #[ink::test]
+        fn escrow_change_deltas_sum_to_total() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, django(), 10).is_ok());
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.cancel_buy_intention().is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            let changes = emitted_events::<EscrowChanged>();
+            let ledger: i128 = changes
+                .iter()
+                .map(|change| {
+                    let delta = change.delta as i128;
+                    if change.is_increase {
+                        delta
+                    } else {
+                        -delta
+                    }
+                })
+                .sum();
+
+            assert_eq!(changes.len(), 5);
+            assert_eq!(ledger, contract.total_escrowed as i128);
+            assert_eq!(contract.total_escrowed, 10);
+        }
+
+        #[ink::test]
+        fn only_owner_reads_any_buyer_delivery() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(matches!(
+                contract.get_buyer_delivery(bob()),
+                Err(Error::CallerIsNotOwner)
+            ));
+
+            ink::env::test::set_caller::<Environment>(alice());
+            let delivery = contract.get_buyer_delivery(bob()).unwrap();
+            assert_eq!(
+                delivery.location,
+                contract.buyers.get(bob()).unwrap().location
+            );
+            assert!(matches!(
+                contract.get_buyer_delivery(charlie()),
+                Err(Error::NotOnBuyersList)
+            ));
+        }
+
+        #[ink::test]
+        fn tags_are_lowercased_and_deduplicated() {
+            let mut contract = publish_with(
+                None,
+                vec!["Rock".to_string(), "rock".to_string(), "Indie".to_string()],
+            );
+            assert_eq!(
+                contract.song_info.tags,
+                vec!["rock".to_string(), "indie".to_string()]
+            );
+            assert!(contract.has_tag("ROCK".to_string()));
+
+            let mut tags: Vec<String> = (0..MAX_TAGS).map(|i| i.to_string()).collect();
+            tags.push("0".to_string());
+            assert!(contract.set_tags(tags).is_ok());
+            assert_eq!(contract.song_info.tags.len(), MAX_TAGS);
+
+            let too_many: Vec<String> = (0..=MAX_TAGS).map(|i| i.to_string()).collect();
+            assert_eq!(contract.set_tags(too_many), Err(Error::TooManyTags));
+        }
+
+        #[ink::test]
+        fn balance_covers_escrow_and_pending_withdrawals() {
+            let mut contract = publish();
+            assert!(contract
+                .set_payees(vec![(charlie(), 7_000), (django(), 3_000)])
+                .is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(post_intention_as(&mut contract, default_accounts().eve, 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.withdraw().is_ok());
+
+            let financials = contract.financials();
+            assert_eq!(financials.total_revenue, 10);
+            assert_eq!(financials.total_escrowed, 10);
+            assert_eq!(financials.pending_withdrawals, 3);
+            assert!(
+                financials.balance >= financials.total_escrowed + financials.pending_withdrawals
+            );
+        }
+
+        #[ink::test]
+        fn can_buy_accepts_new_account() {
+            let contract = publish();
+
+            assert_eq!(contract.can_buy(bob()), Ok(()));
+            assert_eq!(contract.can_buy(alice()), Err(Error::CallerIsOwner));
+        }
+
+        #[ink::test]
+        fn can_buy_reports_blacklist_pause_and_delist() {
+            let mut contract = publish();
+            assert!(contract.add_to_blacklist(bob()).is_ok());
+            assert_eq!(contract.can_buy(bob()), Err(Error::Blacklisted));
+            assert_eq!(contract.audit_summary().blacklisted, 1);
+
+            assert!(contract.set_paused(true, None).is_ok());
+            assert_eq!(contract.can_buy(charlie()), Err(Error::ContractPaused));
+
+            assert!(contract.set_paused(false, None).is_ok());
+            assert!(contract.delist_song().is_ok());
+            assert_eq!(contract.can_buy(charlie()), Err(Error::SongDelisted));
+        }
+
+        #[ink::test]
+        fn can_buy_reports_sold_out_and_sale_end() {
+            let mut contract = publish();
+            assert!(contract.set_sale_end(Some(1_000)).is_ok());
+            ink::env::test::set_block_timestamp::<Environment>(1_000);
+            assert_eq!(contract.can_buy(bob()), Err(Error::SaleEnded));
+
+            assert!(contract.set_max_editions(Some(0)).is_ok());
+            assert_eq!(contract.can_buy(bob()), Err(Error::SoldOut));
+        }
+
+        #[ink::test]
+        fn can_buy_reports_existing_buyer_and_intention() {
+            let mut contract = publish_with(Some(2), Vec::new());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+
+            assert_eq!(contract.can_buy(bob()), Err(Error::AlreadyOnList));
+            assert_eq!(contract.can_buy(django()), Err(Error::PendingLimitReached));
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert_eq!(contract.can_buy(bob()), Err(Error::AlreadyBuyer));
+        }
+
+        #[ink::test]
+        fn withdrawal_cannot_spend_escrow() {
+            let mut contract = publish();
+            assert!(contract
+                .set_payees(vec![(charlie(), 7_000), (django(), 3_000)])
+                .is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            let eve = default_accounts().eve;
+            assert!(post_intention_as(&mut contract, eve, 2_000_000).is_ok());
+            ink::env::test::set_account_balance::<Environment>(
+                ink::env::test::callee::<Environment>(),
+                2_000_005,
+            );
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(contract.withdraw(), Err(Error::WouldBreakEscrow));
+            assert_eq!(contract.get_pending_withdrawal(charlie()), 7);
+
+            ink::env::test::set_caller::<Environment>(django());
+            assert!(contract.withdraw().is_ok());
+            assert!(contract.financials().balance >= contract.total_escrowed);
+        }
+
+        #[ink::test]
+        fn get_co_owners_lists_added_accounts() {
+            let mut contract = publish();
+            assert!(contract.add_co_owner(bob()).is_ok());
+            assert!(contract.add_co_owner(charlie()).is_ok());
+            assert_eq!(contract.add_co_owner(bob()), Err(Error::AlreadyOnList));
+
+            ink::env::test::set_caller::<Environment>(django());
+            assert_eq!(contract.get_co_owners(), vec![bob(), charlie()]);
+            assert_eq!(contract.audit_summary().co_owners, 2);
+        }
+
+        #[ink::test]
+        fn publish_record_keeps_construction_values() {
+            ink::env::test::set_block_timestamp::<Environment>(1_700_000_000_000);
+            let mut contract = publish();
+            assert!(contract.set_price(25).is_ok());
+
+            let record = contract.get_publish_record();
+            assert_eq!(record.owner, alice());
+            assert_eq!(record.artist, "Peso Pluma");
+            assert_eq!(record.song_name, "La bebe - ringtone");
+            assert_eq!(record.price, 10);
+            assert_eq!(record.published_at, 1_700_000_000_000);
+        }
+
+        #[ink::test]
+        fn buyer_public_key_includes_posted_at() {
+            let mut contract = publish();
+            ink::env::test::set_block_timestamp::<Environment>(1_700_000_000_000);
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            let pending = contract.get_buyer_public_key(bob()).unwrap();
+            assert_eq!(pending.key, BUYER_PUBLIC_KEY);
+            assert_eq!(pending.posted_at, 1_700_000_000_000);
+        }
+
+        #[ink::test]
+        fn sweep_refunds_only_expired_intentions() {
+            let mut contract = publish();
+            assert!(contract.set_intention_ttl(Some(1_000)).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            ink::env::test::set_block_timestamp::<Environment>(600);
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            ink::env::test::set_block_timestamp::<Environment>(1_200);
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+
+            ink::env::test::set_caller::<Environment>(django());
+            assert_eq!(contract.sweep_expired(10), Ok(1));
+
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert!(contract.possible_buyers_keys.contains(charlie()));
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 10
+            );
+            assert_eq!(contract.total_escrowed, 10);
+        }
+
+        #[ink::test]
+        fn mirror_location_round_trips() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract
+                .set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    Some("https://mirror.example/QmSongAddress".to_string()),
+                    ENCRYPTION_SCHEME.to_string(),
+                    bob(),
+                    None,
+                    None,
+                )
+                .is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert_eq!(
+                contract
+                    .get_address_and_key_buyer()
+                    .unwrap()
+                    .mirror_location,
+                Some("https://mirror.example/QmSongAddress".to_string())
+            );
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(
+                contract
+                    .get_address_and_key_buyer()
+                    .unwrap()
+                    .mirror_location,
+                None
+            );
+        }
+
+        #[ink::test]
+        fn receipt_confirmation_credits_owner() {
+            let mut contract = publish();
+            assert!(contract.set_receipt_required(true).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert_eq!(contract.get_pending_withdrawal(alice()), 0);
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.confirm_receipt().is_ok());
+
+            assert_eq!(contract.get_pending_withdrawal(alice()), 10);
+            assert_eq!(contract.confirm_receipt(), Err(Error::NothingToWithdraw));
+        }
+
+        #[ink::test]
+        fn unconfirmed_proceeds_release_after_timeout() {
+            let mut contract = publish();
+            assert!(contract.set_receipt_required(true).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(
+                contract.release_unconfirmed(bob()),
+                Err(Error::ReceiptWindowOpen)
+            );
+
+            ink::env::test::set_block_timestamp::<Environment>(RECEIPT_CONFIRMATION_PERIOD);
+            assert!(contract.release_unconfirmed(bob()).is_ok());
+            assert_eq!(contract.get_pending_withdrawal(alice()), 10);
+        }
+
+        #[ink::test]
+        fn am_i_admin_covers_owner_and_co_owners() {
+            let mut contract = publish();
+            assert!(contract.add_co_owner(bob()).is_ok());
+            assert!(contract.am_i_admin());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.am_i_admin());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(!contract.am_i_admin());
+        }
+
+        #[ink::test]
+        fn buyer_keeps_license_terms_of_purchase() {
+            let mut contract = publish();
+            assert_eq!(contract.get_config().license_terms_hash, LICENSE_TERMS_HASH);
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert!(contract.set_license_terms([4; 32]).is_ok());
+
+            assert_eq!(contract.get_config().license_terms_hash, [4; 32]);
+            ink::env::test::set_caller::<Environment>(bob());
+            assert_eq!(
+                contract
+                    .get_address_and_key_buyer()
+                    .unwrap()
+                    .license_terms_hash,
+                LICENSE_TERMS_HASH
+            );
+        }
+
+        #[ink::test]
+        fn license_updates_bump_version_for_new_buyers_only() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert!(contract.set_license_terms([4; 32]).is_ok());
+            assert!(contract.set_license_terms([5; 32]).is_ok());
+
+            assert_eq!(contract.get_license_version(), 3);
+            let updates = emitted_events::<LicenseTermsUpdated>();
+            assert_eq!(updates.len(), 2);
+            assert_eq!(updates[1].version, 3);
+            assert_eq!(updates[1].terms_hash, [5; 32]);
+
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+            assert_eq!(contract.buyers.get(bob()).unwrap().license_version, 1);
+            assert_eq!(contract.buyers.get(charlie()).unwrap().license_version, 3);
+        }
+
+        #[ink::test]
+        fn draw_winner_keeps_one_buyer_and_refunds_the_rest() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, django(), 10).is_ok());
+            // Fixed block data makes the draw deterministic
+            ink::env::test::set_block_timestamp::<Environment>(1_700_000_000_000);
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.draw_winner(), Ok(django()));
+
+            assert_eq!(contract.pending_index, vec![django()]);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 10
+            );
+            assert_eq!(contract.total_escrowed, 10);
+            assert_eq!(emitted_events::<IntentionRejected>().len(), 2);
+        }
+
+        #[ink::test]
+        fn intention_age_grows_with_time() {
+            let mut contract = publish();
+            ink::env::test::set_block_timestamp::<Environment>(1_000);
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_block_timestamp::<Environment>(4_000);
+            assert_eq!(contract.intention_age(bob()), Ok(3_000));
+            assert_eq!(
+                contract.intention_age(charlie()),
+                Err(Error::NotOnPossibleBuyersList)
+            );
+        }
+
+        #[ink::test]
+        fn custom_prices_apply_per_buyer() {
+            let mut contract = publish();
+            let entries = vec![(bob(), 5), (charlie(), 7), (django(), 3)];
+
+            assert_eq!(contract.set_custom_prices(entries), Ok(3));
+
+            assert_eq!(contract.get_effective_price(bob()), 5);
+            assert_eq!(contract.get_effective_price(charlie()), 7);
+            assert_eq!(contract.get_effective_price(django()), 3);
+            assert_eq!(contract.get_effective_price(default_accounts().eve), 10);
+            assert_eq!(
+                contract.set_custom_prices(vec![(bob(), 4), (django(), 0)]),
+                Err(Error::ZeroPrice)
+            );
+            assert_eq!(contract.get_effective_price(bob()), 5);
+            assert_eq!(
+                contract.set_custom_prices(vec![(bob(), 5); MAX_CUSTOM_PRICES_BATCH + 1]),
+                Err(Error::BatchTooLarge)
+            );
+        }
+
+        #[ink::test]
+        fn custom_price_cannot_change_after_intention() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(
+                contract.set_custom_prices(vec![(charlie(), 5), (bob(), 20)]),
+                Err(Error::PendingIntentionsExist)
+            );
+            assert_eq!(contract.get_effective_price(bob()), 10);
+            assert_eq!(contract.get_effective_price(charlie()), 10);
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert_eq!(contract.set_custom_prices(vec![(bob(), 20)]), Ok(1));
+        }
+
+        #[ink::test]
+        fn rating_count_counts_distinct_raters() {
+            let mut contract = publish();
+
+            for buyer in [bob(), charlie(), django()] {
+                assert!(post_intention_as(&mut contract, buyer, 10).is_ok());
+                assert!(approve_as_owner(&mut contract, buyer).is_ok());
+                ink::env::test::set_caller::<Environment>(buyer);
+                assert!(contract.rate_song(4).is_ok());
+            }
+            assert!(contract.rate_song(5).is_ok());
+
+            assert_eq!(contract.get_rating_count(), 3);
+        }
+
+        #[ink::test]
+        fn removed_discount_code_no_longer_applies() {
+            let mut contract = publish();
+            assert!(contract
+                .add_discount_code(code_hash("SUMMER25"), 2_500)
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(8);
+            assert!(contract
+                .post_buy_intention_with_code(
+                    BUYER_PUBLIC_KEY.to_string(),
+                    BOB_KEY_PROOF,
+                    "SUMMER25".to_string()
+                )
+                .is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.remove_discount_code(code_hash("SUMMER25")).is_ok());
+            let removed = emitted_events::<DiscountCodeRemoved>();
+            assert_eq!(removed[0].code_hash, code_hash("SUMMER25"));
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            ink::env::test::transfer_in::<Environment>(8);
+            assert_eq!(
+                contract.post_buy_intention_with_code(
+                    BUYER_PUBLIC_KEY.to_string(),
+                    CHARLIE_KEY_PROOF,
+                    "SUMMER25".to_string()
+                ),
+                Err(Error::InsufficientBalance)
+            );
+            assert!(!contract.is_valid_discount_code("SUMMER25".to_string()));
+        }
+
+        #[ink::test]
+        fn blacklisted_attempt_is_rejected_and_recorded() {
+            let mut contract = publish();
+            assert!(contract.add_to_blacklist(bob()).is_ok());
+
+            assert_eq!(
+                post_intention_as(&mut contract, bob(), 10),
+                Err(Error::Blacklisted)
+            );
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert!(emitted_events::<BlockedAttempt>().is_empty());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.record_blocked_attempt(bob()).is_ok());
+            let attempts = emitted_events::<BlockedAttempt>();
+            assert_eq!(attempts.len(), 1);
+            assert_eq!(attempts[0].account, bob());
+
+            assert_eq!(
+                contract.record_blocked_attempt(charlie()),
+                Err(Error::NotBlacklisted)
+            );
+
+            assert!(contract.decommission().is_ok());
+            assert_eq!(
+                contract.record_blocked_attempt(bob()),
+                Err(Error::Decommissioned)
+            );
+        }
+
+        #[ink::test]
+        fn time_until_sale_end_counts_down_to_zero() {
+            let mut contract = publish();
+            assert_eq!(contract.time_until_sale_end(), None);
+            assert!(contract.set_sale_end(Some(5_000)).is_ok());
+
+            ink::env::test::set_block_timestamp::<Environment>(1_000);
+            assert_eq!(contract.time_until_sale_end(), Some(4_000));
+            ink::env::test::set_block_timestamp::<Environment>(4_500);
+            assert_eq!(contract.time_until_sale_end(), Some(500));
+            ink::env::test::set_block_timestamp::<Environment>(6_000);
+            assert_eq!(contract.time_until_sale_end(), Some(0));
+        }
+
+        #[ink::test]
+        fn watermark_image_updates_alone() {
+            let mut contract = publish();
+            assert_eq!(
+                contract.set_watermark_image(String::new()),
+                Err(Error::EmptyImageAddress)
+            );
+
+            assert!(contract
+                .set_watermark_image("QmNewCoverArt".to_string())
+                .is_ok());
+
+            let info = contract.get_song_info().song_info;
+            assert_eq!(info.watermark_image_ipfs, "QmNewCoverArt");
+            assert_eq!(info.song_name, "La bebe - ringtone");
+            assert_eq!(info.artist_name, "Peso Pluma");
+            assert_eq!(info.album, "Genesis");
+            assert_eq!(info.song_duration, "3:45");
+            assert_eq!(info.image_content_hash, IMAGE_HASH);
+            assert_eq!(
+                emitted_events::<WatermarkUpdated>()[0].watermark_image_ipfs,
+                "QmNewCoverArt"
+            );
+        }
+
+        #[ink::test]
+        fn my_escrow_follows_the_callers_intention() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 12).is_ok());
+            assert_eq!(contract.my_escrow(), 12);
+
+            assert!(contract.cancel_buy_intention().is_ok());
+            assert_eq!(contract.my_escrow(), 0);
+        }
+
+        #[ink::test]
+        fn expired_rental_emits_event_on_check() {
+            let mut contract = publish();
+            assert!(contract.set_rental_duration(Some(1_000)).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert_eq!(contract.check_access(), Ok(true));
+            assert!(emitted_events::<AccessExpiredEvent>().is_empty());
+
+            ink::env::test::set_block_timestamp::<Environment>(1_000);
+            assert!(matches!(
+                contract.get_address_and_key_buyer(),
+                Err(Error::AccessExpired)
+            ));
+            assert_eq!(contract.check_access(), Ok(false));
+
+            let expired = emitted_events::<AccessExpiredEvent>();
+            assert_eq!(expired.len(), 1);
+            assert_eq!(expired[0].buyer, bob());
+            assert_eq!(expired[0].expired_at, 1_000);
+        }
+
+        #[ink::test]
+        fn deposit_is_held_apart_and_released() {
+            let mut contract = publish();
+            contract.deposit_amount = 5;
+            assert_eq!(
+                post_intention_as(&mut contract, bob(), 10),
+                Err(Error::InsufficientBalance)
+            );
+
+            assert!(post_intention_as(&mut contract, bob(), 15).is_ok());
+            assert_eq!(contract.my_escrow(), 10);
+            assert_eq!(contract.get_deposit(bob()), 5);
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert_eq!(contract.get_deposit(bob()), 5);
+
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+            assert!(contract.release_deposit(bob()).is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 5
+            );
+            assert_eq!(contract.release_deposit(bob()), Err(Error::NoDeposit));
+        }
+
+        #[ink::test]
+        fn gifted_deposit_is_released_to_the_payer() {
+            let mut contract = publish();
+            contract.deposit_amount = 5;
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(15);
+            assert!(contract
+                .post_buy_intention_for(charlie(), BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF)
+                .is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+            let charlie_balance =
+                ink::env::test::get_account_balance::<Environment>(charlie()).unwrap();
+
+            assert!(contract.release_deposit(charlie()).is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 5
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(charlie()).unwrap(),
+                charlie_balance
+            );
+        }
+
+        #[ink::test]
+        fn decommission_requires_released_deposits() {
+            let mut contract = publish();
+            contract.deposit_amount = 5;
+            assert!(post_intention_as(&mut contract, bob(), 15).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(contract.decommission(), Err(Error::DepositsHeld));
+
+            assert!(contract.release_deposit(bob()).is_ok());
+            assert!(contract.decommission().is_ok());
+        }
+
+        #[ink::test]
+        fn export_deliveries_includes_all_fields() {
+            let mut contract = publish();
+            ink::env::test::set_block_timestamp::<Environment>(1_700_000_000_000);
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, charlie()).is_ok());
+
+            let page = contract.export_deliveries(1, 10).unwrap();
+
+            assert_eq!(page.len(), 1);
+            let (buyer, delivery, purchased_at) = &page[0];
+            assert_eq!(*buyer, charlie());
+            assert_eq!(delivery.location, "QmSongAddress");
+            assert_eq!(delivery.key, "encrypted-symmetric-key");
+            assert_eq!(delivery.license_terms_hash, LICENSE_TERMS_HASH);
+            assert_eq!(*purchased_at, 1_700_000_000_000);
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(matches!(
+                contract.export_deliveries(0, 10),
+                Err(Error::CallerIsNotOwner)
+            ));
+        }
+
+        #[ink::test]
+        fn frozen_metadata_blocks_edits() {
+            let mut contract = publish();
+            assert!(!contract.is_metadata_frozen());
+
+            assert!(contract.freeze_metadata().is_ok());
+
+            assert!(contract.is_metadata_frozen());
+            assert_eq!(
+                contract.set_watermark_image("QmNewCoverArt".to_string()),
+                Err(Error::MetadataFrozen)
+            );
+            assert_eq!(
+                contract.set_extra_metadata(vec![1, 2, 3]),
+                Err(Error::MetadataFrozen)
+            );
+            assert_eq!(
+                contract.set_tags(vec!["rock".to_string()]),
+                Err(Error::MetadataFrozen)
+            );
+        }
+
+        #[ink::test]
+        fn owner_gated_messages_reject_other_accounts() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(
+                contract.set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    None,
+                    ENCRYPTION_SCHEME.to_string(),
+                    bob(),
+                    None,
+                    None,
+                ),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert!(matches!(
+                contract.get_buyer_public_key(bob()),
+                Err(Error::CallerIsNotOwner)
+            ));
+            assert_eq!(contract.set_price(20), Err(Error::CallerIsNotOwner));
+            assert_eq!(
+                contract.set_paused(true, None),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(
+                contract.reject_intention(bob()),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(
+                contract.add_to_blacklist(bob()),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(
+                contract.add_co_owner(charlie()),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(contract.freeze_metadata(), Err(Error::CallerIsNotOwner));
+            assert_eq!(
+                contract.report_payout_failure(),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(
+                contract.record_blocked_attempt(bob()),
+                Err(Error::CallerIsNotOwner)
+            );
+            assert_eq!(contract.draw_winner(), Err(Error::CallerIsNotOwner));
+            assert_eq!(contract.decommission(), Err(Error::CallerIsNotOwner));
+            assert!(contract.possible_buyers_keys.contains(bob()));
+        }
+
+        #[ink::test]
+        fn delivery_commitment_matches_received_key_and_location() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract
+                .set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    None,
+                    ENCRYPTION_SCHEME.to_string(),
+                    bob(),
+                    None,
+                    None,
+                )
+                .is_ok());
+
+            let mut expected = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &("encrypted-symmetric-key", "QmSongAddress"),
+                &mut expected,
+            );
+            let mut tampered = [0u8; 32];
+            ink::env::hash_encoded::<Blake2x256, _>(
+                &("encrypted-symmetric-key", "QmOtherAddress"),
+                &mut tampered,
+            );
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert_eq!(contract.verify_delivery(expected), Ok(true));
+            assert_eq!(contract.verify_delivery(tampered), Ok(false));
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(
+                contract.verify_delivery(expected),
+                Err(Error::NotOnBuyersList)
+            );
+        }
+
+        #[ink::test]
+        fn payment_method_is_native() {
+            let contract = publish();
+
+            assert_eq!(contract.get_payment_method(), None);
+        }
+
+        #[ink::test]
+        fn set_price_respects_max_price() {
+            let mut contract = publish();
+
+            assert!(contract.set_max_price(Some(100)).is_ok());
+
+            assert!(contract.set_price(100).is_ok());
+            assert_eq!(contract.set_price(101), Err(Error::PriceAboveCap));
+            assert_eq!(contract.price, 100);
+
+            assert!(contract.set_max_price(None).is_ok());
+            assert!(contract.set_price(101).is_ok());
+        }
+
+        #[ink::test]
+        fn forget_me_removes_pending_key() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            assert!(contract.forget_me().is_ok());
+
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert_eq!(contract.pending_count, 0);
+            assert_eq!(contract.total_escrowed, 0);
+
+            let forgotten = emitted_events::<BuyerKeyForgotten>();
+            assert_eq!(forgotten.len(), 1);
+            assert_eq!(forgotten[0].buyer, bob());
+            assert_eq!(forgotten[0].refunded, 10);
+
+            assert_eq!(contract.forget_me(), Err(Error::NotOnPossibleBuyersList));
+        }
+
+        #[ink::test]
+        fn lifetime_buyers_survives_revoke() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract
+                .set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    None,
+                    ENCRYPTION_SCHEME.to_string(),
+                    bob(),
+                    None,
+                    None,
+                )
+                .is_ok());
+            assert_eq!(contract.buyers_count, 1);
+            assert_eq!(contract.get_lifetime_buyers(), 1);
+
+            assert!(contract.revoke_buyer(bob()).is_ok());
+
+            assert_eq!(contract.buyers_count, 0);
+            assert!(contract.buyers_index.is_empty());
+            assert_eq!(contract.get_lifetime_buyers(), 1);
+            assert_eq!(contract.revoke_buyer(bob()), Err(Error::NotOnBuyersList));
+
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(contract.buyers_count, 1);
+            assert_eq!(contract.get_lifetime_buyers(), 1);
         }
 
-        #[ink(message)]
-        pub fn get_address_and_key_buyer(&self) -> ClientResult<DistributedStorageInfo> {
+        #[ink::test]
+        fn revoke_requires_settled_funds_and_clears_buyer_state() {
+            let mut contract = publish();
+            assert!(contract.set_receipt_required(true).is_ok());
+            contract.deposit_amount = 5;
+            assert!(post_intention_as(&mut contract, bob(), 15).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
 
-            if !self.buyers.contains(self.env().caller()) {
-                return Err(Error::NotOnBuyersList)
-            }
+            assert_eq!(contract.revoke_buyer(bob()), Err(Error::ProceedsHeld));
 
-            let buyer_data = self.buyers.get(self.env().caller());
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.confirm_receipt().is_ok());
+            assert!(contract.rate_song(4).is_ok());
+            assert!(contract
+                .request_redelivery(BUYER_PUBLIC_KEY.to_string(), BOB_KEY_PROOF)
+                .is_ok());
 
-            match buyer_data {
-                None => return Err(Error::NotOnBuyersList),
-                Some(data) => return Ok(data)
-            }
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.revoke_buyer(bob()), Err(Error::DepositsHeld));
+            assert!(contract.release_deposit(bob()).is_ok());
+            assert!(contract.set_delivery_chunk(bob(), 0, vec![1, 2]).is_ok());
+
+            assert!(contract.revoke_buyer(bob()).is_ok());
+
+            assert_eq!(contract.get_rating_count(), 0);
+            assert_eq!(contract.get_average_rating(), None);
+            assert_eq!(contract.get_redelivery_requests(), Ok(vec![]));
+            assert!(contract.delivery_chunks.get((bob(), 0)).is_none());
+            assert!(contract.decommission().is_ok());
         }
-        //------------------------------HELPERS------------------------------
 
-        fn is_caller_owner(&self) -> bool {
-            let caller = self.env().caller();
-            return caller == self.owner;
+        #[ink::test]
+        fn compute_fee_matches_fee_bps() {
+            let mut contract = publish();
+            assert_eq!(contract.compute_fee(1_000), 0);
+
+            assert!(contract.set_platform_fee(django(), 250).is_ok());
+            assert_eq!(contract.compute_fee(1_000), 25);
+            assert_eq!(contract.compute_fee(99), 2);
+
+            assert!(contract.set_platform_fee(django(), 5_000).is_ok());
+            assert_eq!(contract.compute_fee(Balance::MAX), Balance::MAX / 2);
+
+            assert!(contract.set_platform_fee(django(), 10_000).is_ok());
+            assert_eq!(contract.compute_fee(Balance::MAX), Balance::MAX);
         }
-    }
 
-    //------------------------------TESTS------------------------------
+        #[ink::test]
+        fn sale_completed_reports_the_split() {
+            let mut contract = publish();
+            assert!(contract.set_price(100).is_ok());
+            assert!(contract.set_platform_fee(django(), 1_000).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 100).is_ok());
 
-    #[cfg(test)]
-    mod tests {
-        use ink::primitives::AccountId;
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
 
-        use super::*;
+            let sales = emitted_events::<SaleCompleted>();
+            assert_eq!(sales.len(), 1);
+            assert_eq!(sales[0].buyer, bob());
+            assert_eq!(sales[0].price_paid, 100);
+            assert_eq!(sales[0].fee, 10);
+            assert_eq!(sales[0].net_to_owner, 90);
+        }
 
-        // We define some helper Accounts to make our tests more readable
-        fn default_accounts() -> ink::env::test::DefaultAccounts<Environment> {
-            ink::env::test::default_accounts::<Environment>()
+        #[ink::test]
+        fn granted_extension_outlives_original_expiry() {
+            let mut contract = publish();
+            assert!(contract.set_rental_duration(Some(1_000)).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert!(contract.grant_extension(bob(), 500).is_ok());
+            assert_eq!(
+                contract.grant_extension(charlie(), 500),
+                Err(Error::NotOnBuyersList)
+            );
+
+            let extended = emitted_events::<AccessExtended>();
+            assert_eq!(extended.len(), 1);
+            assert_eq!(extended[0].buyer, bob());
+            assert_eq!(extended[0].expires_at, 1_500);
+
+            ink::env::test::set_block_timestamp::<Environment>(1_200);
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.get_address_and_key_buyer().is_ok());
         }
 
-        fn alice() -> AccountId {
-            default_accounts().alice
+        #[ink::test]
+        fn pause_reason_is_set_and_cleared() {
+            let mut contract = publish();
+            assert_eq!(contract.get_pause_reason(), None);
+
+            assert!(contract
+                .set_paused(true, Some("Re-encoding the master".to_string()))
+                .is_ok());
+            assert_eq!(
+                contract.get_pause_reason(),
+                Some("Re-encoding the master".to_string())
+            );
+
+            assert!(contract.set_paused(false, None).is_ok());
+            assert_eq!(contract.get_pause_reason(), None);
+
+            assert_eq!(
+                contract.set_paused(true, Some("a".repeat(MAX_PAUSE_REASON_LEN + 1))),
+                Err(Error::PauseReasonTooLong)
+            );
+            assert!(!contract.paused);
         }
 
-        fn bob() -> AccountId {
-            default_accounts().bob
+        #[ink::test]
+        fn recovery_is_timelocked() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(contract.initiate_recovery(), Err(Error::NotRecoveryAddress));
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.set_recovery_address(Some(charlie())).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(contract.claim_recovery(), Err(Error::NoRecoveryInProgress));
+            assert!(contract.initiate_recovery().is_ok());
+
+            ink::env::test::set_block_timestamp::<Environment>(RECOVERY_DELAY - 1);
+            assert_eq!(contract.claim_recovery(), Err(Error::RecoveryTimelocked));
+            assert_eq!(contract.owner, alice());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.set_recovery_address(Some(charlie())).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            ink::env::test::set_block_timestamp::<Environment>(RECOVERY_DELAY);
+            assert_eq!(contract.claim_recovery(), Err(Error::NoRecoveryInProgress));
+
+            let initiated = emitted_events::<RecoveryInitiated>();
+            assert_eq!(initiated.len(), 1);
+            assert_eq!(initiated[0].recovery_address, charlie());
+            assert_eq!(initiated[0].claimable_at, RECOVERY_DELAY);
+            assert_eq!(emitted_events::<RecoveryCancelled>().len(), 1);
         }
 
-        fn charlie() -> AccountId {
-            default_accounts().charlie
+        #[ink::test]
+        fn recovery_address_claims_ownership_after_delay() {
+            let mut contract = publish();
+            assert!(contract.set_recovery_address(Some(charlie())).is_ok());
+            assert!(contract.set_royalty_recipient(django()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.initiate_recovery().is_ok());
+
+            ink::env::test::set_block_timestamp::<Environment>(RECOVERY_DELAY);
+            assert!(contract.claim_recovery().is_ok());
+
+            assert_eq!(contract.owner, charlie());
+            assert_eq!(contract.payout_address, charlie());
+            assert_eq!(contract.fee_recipient, charlie());
+            assert_eq!(contract.royalty_recipient, django());
+            assert!(contract.set_price(20).is_ok());
+
+            let recovered = emitted_events::<OwnershipRecovered>();
+            assert_eq!(recovered.len(), 1);
+            assert_eq!(recovered[0].previous_owner, alice());
+            assert_eq!(recovered[0].new_owner, charlie());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.set_price(30), Err(Error::CallerIsNotOwner));
         }
 
-        fn django() -> AccountId {
-            default_accounts().django
+        #[ink::test]
+        fn intention_event_carries_key_len() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            let intents = emitted_events::<SongBuyIntent>();
+            assert_eq!(intents.len(), 1);
+            assert_eq!(intents[0].from, bob());
+            assert_eq!(intents[0].key_len, BUYER_PUBLIC_KEY.len() as u32);
         }
 
         #[ink::test]
-        fn publish_works() {
-            let contract = ContractPublish::new_publish(
-                "La bebe - ringtone".to_string(),
-                1,
-                "QmZ41fazG24A6H4bicrM2cTPjLWxxsX8tQkrAPzCu2e8AB".to_string(),
-                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o".to_string(),
+        fn buyer_profile_of_confirmed_buyer() {
+            let mut contract = publish();
+            ink::env::test::set_block_timestamp::<Environment>(100);
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            let pending = contract.buyer_profile(bob()).unwrap();
+            assert_eq!(pending.state, 1);
+            assert_eq!(pending.escrow, 10);
+            assert_eq!(pending.posted_at, Some(100));
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.rate_song(4).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            let profile = contract.buyer_profile(bob()).unwrap();
+            assert_eq!(profile.state, 2);
+            assert_eq!(profile.escrow, 0);
+            assert_eq!(profile.posted_at, None);
+            assert_eq!(profile.rating, Some(4));
+            let delivery = profile.delivery.unwrap();
+            assert_eq!(delivery.location, "QmSongAddress");
+            assert_eq!(delivery.purchased_at, 100);
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(matches!(
+                contract.buyer_profile(bob()),
+                Err(Error::CallerIsNotOwner)
+            ));
+        }
+
+        #[ink::test]
+        fn display_rate_round_trips() {
+            let mut contract = publish();
+            assert_eq!(contract.get_display_rate(), None);
+
+            assert!(contract
+                .set_display_rate(Some(("USD".to_string(), 1_250_000)))
+                .is_ok());
+
+            assert_eq!(
+                contract.get_display_rate(),
+                Some(("USD".to_string(), 1_250_000))
+            );
+        }
+
+        #[ink::test]
+        fn my_access_is_active_until_expiry() {
+            let mut contract = publish();
+            assert!(contract.set_rental_duration(Some(1_000)).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract.is_my_access_active());
+
+            ink::env::test::set_block_timestamp::<Environment>(1_000);
+            assert!(!contract.is_my_access_active());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(!contract.is_my_access_active());
+        }
+
+        #[ink::test]
+        fn custom_price_emits_event() {
+            let mut contract = publish();
+
+            assert_eq!(
+                contract.set_custom_prices(vec![(bob(), 5), (charlie(), 7)]),
+                Ok(2)
+            );
+
+            let set = emitted_events::<CustomPriceSet>();
+            assert_eq!(set.len(), 2);
+            assert_eq!((set[0].buyer, set[0].price), (bob(), 5));
+            assert_eq!((set[1].buyer, set[1].price), (charlie(), 7));
+        }
+
+        #[ink::test]
+        fn get_payees_returns_configured_splits() {
+            let mut contract = publish();
+            assert!(contract.get_payees().is_empty());
+
+            let payees = vec![(charlie(), 7_000), (django(), 3_000)];
+            assert!(contract.set_payees(payees.clone()).is_ok());
+
+            let configured = contract.get_payees();
+            assert_eq!(configured, payees);
+            assert_eq!(
+                configured
+                    .iter()
+                    .map(|(_, share)| u32::from(*share))
+                    .sum::<u32>(),
+                10_000
+            );
+        }
+
+        #[ink::test]
+        fn is_co_owner_excludes_primary_owner() {
+            let mut contract = publish();
+            assert!(contract.add_co_owner(bob()).is_ok());
+
+            assert!(contract.is_co_owner(bob()));
+            assert!(!contract.is_co_owner(alice()));
+            assert!(!contract.is_co_owner(charlie()));
+        }
+
+        #[ink::test]
+        fn redelivery_is_requested_and_cleared() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert_eq!(
+                contract.request_redelivery(BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF),
+                Err(Error::NotOnBuyersList)
             );
+
+            ink::env::test::set_caller::<Environment>(bob());
+            assert!(contract
+                .request_redelivery(BUYER_PUBLIC_KEY.to_string(), BOB_KEY_PROOF)
+                .is_ok());
+
+            let requested = emitted_events::<RedeliveryRequested>();
+            assert_eq!(requested.len(), 1);
+            assert_eq!(requested[0].buyer, bob());
+            assert_eq!(requested[0].new_public_key, BUYER_PUBLIC_KEY);
+
+            ink::env::test::set_caller::<Environment>(alice());
             assert_eq!(
-                contract.recover_hash_address(),
-                "QmZ41fazG24A6H4bicrM2cTPjLWxxsX8tQkrAPzCu2e8AB"
+                contract.get_redelivery_requests(),
+                Ok(vec![(bob(), BUYER_PUBLIC_KEY.to_string())])
             );
+
+            assert!(contract
+                .update_delivery(
+                    bob(),
+                    "re-encrypted-key".to_string(),
+                    "QmSongAddress".to_string()
+                )
+                .is_ok());
+            assert_eq!(contract.get_redelivery_requests(), Ok(vec![]));
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().ok().unwrap();
+            assert_eq!(delivery.key, "re-encrypted-key");
+        }
+
+        #[ink::test]
+        fn redelivery_requires_key_ownership_proof() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
             assert_eq!(
-                contract.recover_image_address(),
-                "QmZ2Fg6zDt8p7SLsuVAL2spGAAY2rPp7JShAY3Xk6Ndt8o"
+                contract.request_redelivery("rotated-key".to_string(), BOB_KEY_PROOF),
+                Err(Error::KeyOwnershipProofInvalid)
+            );
+            assert_eq!(
+                contract.request_redelivery(BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF),
+                Err(Error::KeyOwnershipProofInvalid)
+            );
+            assert!(emitted_events::<RedeliveryRequested>().is_empty());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(contract.get_redelivery_requests(), Ok(vec![]));
+        }
+
+        #[ink::test]
+        fn overpayment_is_refunded_and_recorded() {
+            let mut contract = publish();
+            assert!(contract.set_refund_overpayments(true).is_ok());
+            assert_eq!(contract.last_overpayment(bob()), 0);
+            let balance_before = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+
+            assert!(post_intention_as(&mut contract, bob(), 25).is_ok());
+
+            assert_eq!(contract.last_overpayment(bob()), 15);
+            assert_eq!(contract.escrowed_amount(bob()), 10);
+            assert_eq!(contract.total_escrowed, 10);
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                balance_before - 10
+            );
+        }
+
+        #[ink::test]
+        fn created_block_is_the_construction_block() {
+            ink::env::test::set_block_number::<Environment>(42);
+            let contract = publish();
+
+            ink::env::test::advance_block::<Environment>();
+            assert_eq!(contract.get_created_block(), 42);
+        }
+
+        #[ink::test]
+        fn royalty_info_uses_royalty_recipient() {
+            let mut contract = publish();
+            assert!(contract.set_resale_royalty(750).is_ok());
+            assert!(contract.set_royalty_recipient(django()).is_ok());
+
+            assert_eq!(contract.royalty_info(2_000), (django(), 150));
+        }
+
+        #[ink::test]
+        fn batch_blacklist_skips_existing_entries() {
+            let mut contract = publish();
+            assert!(contract.add_to_blacklist(bob()).is_ok());
+
+            assert_eq!(
+                contract.add_many_to_blacklist(vec![bob(), charlie(), django()]),
+                Ok(2)
+            );
+
+            assert_eq!(contract.audit_summary().blacklisted, 3);
+            assert_eq!(emitted_events::<BatchBlacklisted>()[0].count, 2);
+            assert_eq!(
+                contract.add_many_to_blacklist(vec![bob(); MAX_BLACKLIST_BATCH + 1]),
+                Err(Error::BatchTooLarge)
+            );
+        }
+
+        #[ink::test]
+        fn buying_open_follows_pause_and_sale_end() {
+            let mut contract = publish();
+            assert!(contract.buying_open());
+
+            assert!(contract.set_paused(true, None).is_ok());
+            assert!(!contract.buying_open());
+            assert!(contract.set_paused(false, None).is_ok());
+            assert!(contract.buying_open());
+
+            assert!(contract.set_sale_end(Some(1_000)).is_ok());
+            assert!(contract.buying_open());
+            ink::env::test::set_block_timestamp::<Environment>(1_000);
+            assert!(!contract.buying_open());
+        }
+
+        #[ink::test]
+        fn buying_closes_when_sold_out() {
+            let mut contract = publish();
+            assert!(contract.set_max_editions(Some(1)).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(contract.buying_open());
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(!contract.buying_open());
+        }
+
+        #[ink::test]
+        fn buying_closes_when_delisted() {
+            let mut contract = publish();
+
+            assert!(contract.delist_song().is_ok());
+            assert!(!contract.buying_open());
+        }
+
+        #[ink::test]
+        fn direct_sale_delivers_in_one_call() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(alice());
+            ink::env::test::transfer_in::<Environment>(10);
+            assert!(contract
+                .direct_sale(
+                    bob(),
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    ENCRYPTION_SCHEME.to_string()
+                )
+                .is_ok());
+
+            assert_eq!(contract.pending_count, 0);
+            assert_eq!(contract.total_escrowed, 0);
+            assert_eq!(contract.total_revenue, 10);
+            assert_eq!(emitted_events::<SaleCompleted>()[0].price_paid, 10);
+
+            ink::env::test::transfer_in::<Environment>(10);
+            assert_eq!(
+                contract.direct_sale(
+                    alice(),
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    ENCRYPTION_SCHEME.to_string()
+                ),
+                Err(Error::BuyerIsOwner)
+            );
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().ok().unwrap();
+            assert_eq!(delivery.location, "QmSongAddress");
+        }
+
+        #[ink::test]
+        fn direct_sale_leaves_no_intention_and_refunds_overpayment() {
+            let mut contract = publish();
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.set_refund_overpayments(true).is_ok());
+
+            ink::env::test::transfer_in::<Environment>(10);
+            assert_eq!(
+                contract.direct_sale(
+                    bob(),
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    "ROT13".to_string()
+                ),
+                Err(Error::UnsupportedScheme)
+            );
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert_eq!(contract.pending_count, 0);
+
+            ink::env::test::transfer_in::<Environment>(15);
+            assert!(contract
+                .direct_sale(
+                    bob(),
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    ENCRYPTION_SCHEME.to_string()
+                )
+                .is_ok());
+
+            assert_eq!(contract.total_revenue, 10);
+            assert_eq!(contract.last_overpayment(alice()), 5);
+            assert!(!contract.possible_buyers_keys.contains(bob()));
+            assert!(contract.buyers.contains(bob()));
+        }
+
+        #[ink::test]
+        fn delivery_records_a_supported_scheme() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert_eq!(
+                contract.set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    None,
+                    "ROT13".to_string(),
+                    bob(),
+                    None,
+                    None,
+                ),
+                Err(Error::UnsupportedScheme)
+            );
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            let delivery = contract.get_address_and_key_buyer().ok().unwrap();
+            assert_eq!(delivery.encryption_scheme, ENCRYPTION_SCHEME);
+        }
+
+        #[ink::test]
+        fn editions_owned_counts_the_delivery() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert_eq!(contract.editions_owned(bob()), 0);
+
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+
+            assert_eq!(contract.editions_owned(bob()), 1);
+            assert_eq!(contract.editions_owned(charlie()), 0);
+        }
+
+        #[ink::test]
+        fn approvals_proceed_while_intentions_are_paused() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.set_intentions_paused(true).is_ok());
+            assert!(!contract.buying_open());
+
+            assert_eq!(
+                post_intention_as(&mut contract, charlie(), 10),
+                Err(Error::IntentionsPaused)
+            );
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert_eq!(contract.buyers_count, 1);
+
+            assert!(contract.set_intentions_paused(false).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+        }
+
+        #[ink::test]
+        fn global_pause_also_stops_approvals_but_not_cancels() {
+            let mut contract = publish();
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+            assert!(post_intention_as(&mut contract, charlie(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.set_paused(true, None).is_ok());
+
+            assert_eq!(
+                approve_as_owner(&mut contract, bob()),
+                Err(Error::ContractPaused)
+            );
+            assert_eq!(
+                post_intention_as(&mut contract, django(), 10),
+                Err(Error::ContractPaused)
+            );
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.cancel_buy_intention().is_ok());
+
+            ink::env::test::set_caller::<Environment>(alice());
+            assert!(contract.set_paused(false, None).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+        }
+
+        #[ink::test]
+        fn cancelled_gift_refunds_the_payer() {
+            let mut contract = publish();
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(10);
+            assert!(contract
+                .post_buy_intention_for(charlie(), BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF)
+                .is_ok());
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+            let charlie_balance =
+                ink::env::test::get_account_balance::<Environment>(charlie()).unwrap();
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.cancel_buy_intention().is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 10
+            );
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(charlie()).unwrap(),
+                charlie_balance
+            );
+        }
+
+        #[ink::test]
+        fn only_the_payer_tops_up_a_gift() {
+            let mut contract = publish();
+            assert!(contract.set_installments(true).is_ok());
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(4);
+            assert!(contract
+                .post_buy_intention_for(charlie(), BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF)
+                .is_ok());
+
+            assert_eq!(
+                post_intention_as(&mut contract, charlie(), 3),
+                Err(Error::NotPayer)
+            );
+
+            ink::env::test::set_account_balance::<Environment>(django(), 1_000_000);
+            ink::env::test::set_caller::<Environment>(django());
+            ink::env::test::transfer_in::<Environment>(3);
+            assert_eq!(
+                contract.post_buy_intention_for(
+                    charlie(),
+                    BUYER_PUBLIC_KEY.to_string(),
+                    CHARLIE_KEY_PROOF
+                ),
+                Err(Error::NotPayer)
+            );
+
+            ink::env::test::set_caller::<Environment>(bob());
+            ink::env::test::transfer_in::<Environment>(2);
+            assert!(contract
+                .post_buy_intention_for(charlie(), BUYER_PUBLIC_KEY.to_string(), CHARLIE_KEY_PROOF)
+                .is_ok());
+            let bob_balance = ink::env::test::get_account_balance::<Environment>(bob()).unwrap();
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.cancel_buy_intention().is_ok());
+
+            assert_eq!(
+                ink::env::test::get_account_balance::<Environment>(bob()).unwrap(),
+                bob_balance + 6
+            );
+        }
+
+        #[ink::test]
+        fn partial_installments_cannot_be_approved() {
+            let mut contract = publish();
+            assert!(contract.set_installments(true).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 1).is_ok());
+
+            assert_eq!(
+                approve_as_owner(&mut contract, bob()),
+                Err(Error::PaymentIncomplete)
+            );
+
+            assert!(post_intention_as(&mut contract, bob(), 9).is_ok());
+            assert!(approve_as_owner(&mut contract, bob()).is_ok());
+            assert!(contract.buyers.contains(bob()));
+        }
+
+        #[ink::test]
+        fn co_owner_can_approve_but_not_change_settings() {
+            let mut contract = publish();
+            assert!(contract.add_co_owner(charlie()).is_ok());
+            assert!(post_intention_as(&mut contract, bob(), 10).is_ok());
+
+            ink::env::test::set_caller::<Environment>(charlie());
+            assert!(contract.am_i_admin());
+            assert!(contract.get_buyer_public_key(bob()).is_ok());
+            assert!(contract
+                .set_new_allowed_buyer(
+                    "encrypted-symmetric-key".to_string(),
+                    "QmSongAddress".to_string(),
+                    None,
+                    ENCRYPTION_SCHEME.to_string(),
+                    bob(),
+                    None,
+                    None,
+                )
+                .is_ok());
+            assert!(contract.buyers.contains(bob()));
+
+            assert_eq!(contract.set_price(20), Err(Error::CallerIsNotOwner));
+            assert_eq!(
+                contract.add_co_owner(django()),
+                Err(Error::CallerIsNotOwner)
             );
-            assert_eq!(contract.recover_song_name(), "La bebe - ringtone");
-            assert_eq!(contract.recover_song_price(), 1);
         }
     }
 }